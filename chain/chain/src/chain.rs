@@ -3109,6 +3109,111 @@ impl Chain {
         Ok(FinalExecutionOutcomeWithReceiptView { final_outcome, receipts })
     }
 
+    /// Returns hashes of transactions included in blocks of the canonical chain
+    /// at heights `from..=to` whose transaction or receipt outcomes were
+    /// executed by `account_id`. Heights without a block are skipped.
+    ///
+    /// This scans all outcomes in the range, so it is meant for debugging and
+    /// tests rather than for serving user requests.
+    pub fn transactions_affecting_account(
+        &self,
+        account_id: &AccountId,
+        from: BlockHeight,
+        to: BlockHeight,
+    ) -> Result<Vec<CryptoHash>, Error> {
+        let mut transactions = vec![];
+        for height in from..=to {
+            let block_hash = match self.get_block_hash_by_height(height) {
+                Ok(block_hash) => block_hash,
+                Err(Error::DBNotFoundErr(_)) => continue,
+                Err(err) => return Err(err),
+            };
+            let outcomes = self.chain_store.get_block_execution_outcomes(&block_hash)?;
+            for outcome in outcomes.into_values().flatten() {
+                let id = outcome.outcome_with_id.id;
+                if self.chain_store.get_transaction(&id)?.is_none() {
+                    continue;
+                }
+                let mut tx_outcomes = vec![];
+                self.get_recursive_transaction_results(&mut tx_outcomes, &id)?;
+                if tx_outcomes.iter().any(|outcome| &outcome.outcome.executor_id == account_id) {
+                    transactions.push(id);
+                }
+            }
+        }
+        Ok(transactions)
+    }
+
+    /// Returns the height of the first block on the canonical chain whose
+    /// epoch uses a protocol version supporting `feature`, or `None` if the
+    /// feature is not supported at the head.
+    ///
+    /// This scans all heights up to the head, so it is meant for debugging and
+    /// tests rather than for serving user requests.
+    pub fn feature_activation_height(
+        &self,
+        feature: ProtocolFeature,
+    ) -> Result<Option<BlockHeight>, Error> {
+        let head = self.head()?;
+        for height in self.genesis().height()..=head.height {
+            let block_hash = match self.get_block_hash_by_height(height) {
+                Ok(block_hash) => block_hash,
+                Err(Error::DBNotFoundErr(_)) => continue,
+                Err(err) => return Err(err),
+            };
+            let epoch_id = self.get_block_header(&block_hash)?.epoch_id().clone();
+            let protocol_version = self.epoch_manager.get_epoch_protocol_version(&epoch_id)?;
+            if protocol_version >= feature.protocol_version() {
+                return Ok(Some(height));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns `(height, amount, nonrefundable)` balances of the account after
+    /// the blocks at heights `from`, `from + step`, ... up to `to`, skipping
+    /// heights without a block on the canonical chain or at which the account
+    /// does not exist.
+    ///
+    /// Fails with `GCError` if the range starts below the tail of the chain,
+    /// and with `Other` if `step` is zero.
+    pub fn account_balance_history(
+        &self,
+        account_id: &AccountId,
+        from: BlockHeight,
+        to: BlockHeight,
+        step: BlockHeightDelta,
+    ) -> Result<Vec<(BlockHeight, Balance, Balance)>, Error> {
+        if step == 0 {
+            return Err(Error::Other("step must be positive".to_string()));
+        }
+        let tail = self.tail()?;
+        if from < tail {
+            return Err(Error::GCError(format!("height {from} is below the tail {tail}")));
+        }
+        let mut history = vec![];
+        for height in (from..=to).step_by(step as usize) {
+            let block_hash = match self.get_block_hash_by_height(height) {
+                Ok(block_hash) => block_hash,
+                Err(Error::DBNotFoundErr(_)) => continue,
+                Err(err) => return Err(err),
+            };
+            let epoch_id = self.get_block_header(&block_hash)?.epoch_id().clone();
+            let shard_id = self.epoch_manager.account_id_to_shard_id(account_id, &epoch_id)?;
+            let shard_uid = self.epoch_manager.shard_id_to_uid(shard_id, &epoch_id)?;
+            let chunk_extra = self.get_chunk_extra(&block_hash, &shard_uid)?;
+            let trie = self.runtime_adapter.get_view_trie_for_shard(
+                shard_id,
+                &block_hash,
+                *chunk_extra.state_root(),
+            )?;
+            if let Some(account) = near_store::get_account(&trie, account_id)? {
+                history.push((height, account.amount(), account.nonrefundable()));
+            }
+        }
+        Ok(history)
+    }
+
     pub fn check_blocks_final_and_canonical(
         &self,
         block_headers: &[BlockHeader],
@@ -32,8 +32,9 @@ use near_primitives::types::{
 };
 use near_primitives::version::ProtocolVersion;
 use near_primitives::views::{
-    AccessKeyInfoView, CallResult, ContractCodeView, QueryRequest, QueryResponse,
-    QueryResponseKind, ViewApplyState, ViewStateResult,
+    AccessKeyInfoView, AccountRawView, AccountViewResult, CallResult, ContractCodeView,
+    PendingActionView, PendingActionsView, QueryRequest, QueryResponse, QueryResponseKind,
+    ViewApplyState, ViewStateResult,
 };
 use near_store::config::StateSnapshotType;
 use near_store::flat::FlatStorageManager;
@@ -46,6 +47,7 @@ use near_vm_runner::logic::CompiledContractCache;
 use near_vm_runner::precompile_contract;
 use near_vm_runner::ContractCode;
 use node_runtime::adapter::ViewRuntimeAdapter;
+use node_runtime::state_viewer::errors::ViewAccountError;
 use node_runtime::state_viewer::TrieViewer;
 use node_runtime::{
     validate_transaction, verify_and_charge_transaction, ApplyState, Runtime,
@@ -909,6 +911,99 @@ impl RuntimeAdapter for NightshadeRuntime {
                     block_hash: *block_hash,
                 })
             }
+            QueryRequest::ViewAccountRaw { account_id } => {
+                let account =
+                    self.view_account(&shard_uid, *state_root, account_id).map_err(|err| {
+                        crate::near_chain_primitives::error::QueryError::from_view_account_error(
+                            err,
+                            block_height,
+                            *block_hash,
+                        )
+                    })?;
+                Ok(QueryResponse {
+                    kind: QueryResponseKind::AccountRaw(AccountRawView { record: account }),
+                    block_height,
+                    block_hash: *block_hash,
+                })
+            }
+            QueryRequest::ViewAccountPendingActions { account_id } => {
+                let state_update = self.tries.new_trie_update_view(shard_uid, *state_root);
+                let actions =
+                    self.trie_viewer.view_pending_actions(&state_update, account_id).map_err(
+                        |err| crate::near_chain_primitives::error::QueryError::InternalError {
+                            error_message: err.to_string(),
+                            block_height,
+                            block_hash: *block_hash,
+                        },
+                    )?;
+                Ok(QueryResponse {
+                    kind: QueryResponseKind::PendingActions(PendingActionsView {
+                        actions: actions
+                            .into_iter()
+                            .map(|(receipt_id, action)| PendingActionView {
+                                receipt_id,
+                                action: action.into(),
+                            })
+                            .collect(),
+                    }),
+                    block_height,
+                    block_hash: *block_hash,
+                })
+            }
+            // Only the state of `shard_uid` is available, so the query is
+            // rejected if any of `account_ids` belongs to another shard.
+            QueryRequest::ViewAccounts { account_ids } => {
+                for account_id in account_ids {
+                    let shard_id =
+                        self.epoch_manager.account_id_to_shard_id(account_id, epoch_id).map_err(
+                            |err| crate::near_chain_primitives::error::QueryError::InternalError {
+                                error_message: err.to_string(),
+                                block_height,
+                                block_hash: *block_hash,
+                            },
+                        )?;
+                    if shard_id != shard_uid.shard_id() {
+                        return Err(
+                            crate::near_chain_primitives::error::QueryError::InternalError {
+                                error_message: format!(
+                                    "account {account_id} belongs to shard {shard_id}, not to the queried shard {}",
+                                    shard_uid.shard_id()
+                                ),
+                                block_height,
+                                block_hash: *block_hash,
+                            },
+                        );
+                    }
+                }
+                let mut accounts = Vec::with_capacity(account_ids.len());
+                for account_id in account_ids {
+                    let (account, error) =
+                        match self.view_account(&shard_uid, *state_root, account_id) {
+                            Ok(account) => (Some(account.into()), None),
+                            Err(
+                                err @ (ViewAccountError::InvalidAccountId { .. }
+                                | ViewAccountError::AccountDoesNotExist { .. }),
+                            ) => (None, Some(err.to_string())),
+                            Err(err) => {
+                                return Err(crate::near_chain_primitives::error::QueryError::from_view_account_error(
+                                    err,
+                                    block_height,
+                                    *block_hash,
+                                ))
+                            }
+                        };
+                    accounts.push(AccountViewResult {
+                        account_id: account_id.clone(),
+                        account,
+                        error,
+                    });
+                }
+                Ok(QueryResponse {
+                    kind: QueryResponseKind::Accounts(accounts),
+                    block_height,
+                    block_hash: *block_hash,
+                })
+            }
             QueryRequest::ViewCode { account_id } => {
                 let contract_code = self
                     .view_contract_code(&shard_uid,  *state_root, account_id)
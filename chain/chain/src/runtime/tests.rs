@@ -1381,6 +1381,49 @@ fn test_insufficient_stake() {
     assert_eq!(env.last_proposals[0].stake(), 0);
 }
 
+/// A `ViewAccounts` query is only answered if all of its accounts belong to
+/// the queried shard.
+#[test]
+fn test_view_accounts_rejects_accounts_of_other_shards() {
+    let validators = vec!["test1".parse().unwrap(), "test2".parse().unwrap()];
+    let env = TestEnv::new(vec![validators.clone(), validators], 4, false);
+    let epoch_id = &env.head.epoch_id;
+    let shard_id = |account_id: &AccountId| {
+        env.epoch_manager.account_id_to_shard_id(account_id, epoch_id).unwrap()
+    };
+    let first_account_id: AccountId = "test1".parse().unwrap();
+    let other_account_id = (0..100)
+        .map(|i| AccountId::try_from(format!("account{i}")).unwrap())
+        .find(|account_id| shard_id(account_id) != shard_id(&first_account_id))
+        .unwrap();
+    let shard_uid =
+        env.epoch_manager.shard_id_to_uid(shard_id(&first_account_id), epoch_id).unwrap();
+    let query = |account_ids: Vec<AccountId>| {
+        env.runtime.query(
+            shard_uid,
+            &env.state_roots[shard_uid.shard_id() as usize],
+            env.head.height,
+            0,
+            &env.head.prev_block_hash,
+            &env.head.last_block_hash,
+            epoch_id,
+            &QueryRequest::ViewAccounts { account_ids },
+        )
+    };
+
+    let response = query(vec![first_account_id.clone()]).unwrap();
+    assert!(matches!(response.kind, QueryResponseKind::Accounts(accounts) if accounts.len() == 1));
+    let err = query(vec![first_account_id, other_account_id.clone()]).unwrap_err();
+    assert!(
+        matches!(
+            &err,
+            crate::near_chain_primitives::error::QueryError::InternalError { error_message, .. }
+                if error_message.contains(other_account_id.as_str())
+        ),
+        "unexpected error {err:?}"
+    );
+}
+
 /// Check that flat state is included into trie and is not included into view trie, because we can't apply flat
 /// state optimization to view calls.
 #[test]
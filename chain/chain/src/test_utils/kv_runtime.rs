@@ -42,8 +42,9 @@ use near_primitives::types::{
 };
 use near_primitives::version::{ProtocolVersion, PROTOCOL_VERSION};
 use near_primitives::views::{
-    AccessKeyInfoView, AccessKeyList, CallResult, ContractCodeView, EpochValidatorInfo,
-    QueryRequest, QueryResponse, QueryResponseKind, ViewStateResult,
+    AccessKeyInfoView, AccessKeyList, AccountRawView, AccountViewResult, CallResult,
+    ContractCodeView, EpochValidatorInfo, PendingActionsView, QueryRequest, QueryResponse,
+    QueryResponseKind, ViewStateResult,
 };
 use near_store::test_utils::TestTriesBuilder;
 use near_store::{
@@ -1294,6 +1295,67 @@ impl RuntimeAdapter for KeyValueRuntime {
                 block_height,
                 block_hash: *block_hash,
             }),
+            QueryRequest::ViewAccountRaw { account_id } => Ok(QueryResponse {
+                kind: QueryResponseKind::AccountRaw(AccountRawView {
+                    record: Account::new(
+                        self.state.read().unwrap().get(state_root).map_or_else(
+                            || 0,
+                            |state| *state.amounts.get(account_id).unwrap_or(&0),
+                        ),
+                        0,
+                        0,
+                        CryptoHash::default(),
+                        0,
+                        PROTOCOL_VERSION,
+                    ),
+                }),
+                block_height,
+                block_hash: *block_hash,
+            }),
+            QueryRequest::ViewAccountPendingActions { .. } => Ok(QueryResponse {
+                kind: QueryResponseKind::PendingActions(PendingActionsView { actions: vec![] }),
+                block_height,
+                block_hash: *block_hash,
+            }),
+            QueryRequest::ViewAccounts { account_ids } => {
+                let state = self.state.read().unwrap();
+                let amounts = state.get(state_root).map(|state| &state.amounts);
+                Ok(QueryResponse {
+                    kind: QueryResponseKind::Accounts(
+                        account_ids
+                            .iter()
+                            .map(|account_id| {
+                                match amounts.and_then(|amounts| amounts.get(account_id)) {
+                                    Some(amount) => AccountViewResult {
+                                        account_id: account_id.clone(),
+                                        account: Some(
+                                            Account::new(
+                                                *amount,
+                                                0,
+                                                0,
+                                                CryptoHash::default(),
+                                                0,
+                                                PROTOCOL_VERSION,
+                                            )
+                                            .into(),
+                                        ),
+                                        error: None,
+                                    },
+                                    None => AccountViewResult {
+                                        account_id: account_id.clone(),
+                                        account: None,
+                                        error: Some(format!(
+                                            "Account ID #{account_id} does not exist"
+                                        )),
+                                    },
+                                }
+                            })
+                            .collect(),
+                    ),
+                    block_height,
+                    block_hash: *block_hash,
+                })
+            }
             QueryRequest::ViewCode { .. } => Ok(QueryResponse {
                 kind: QueryResponseKind::ViewCode(ContractCodeView {
                     code: vec![],
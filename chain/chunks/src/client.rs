@@ -53,6 +53,11 @@ impl ShardedTransactionPool {
         self.tx_pools.get_mut(&shard_uid).map(|pool| pool.pool_iterator())
     }
 
+    /// Returns the number of transactions in the pool for a given shard.
+    pub fn len(&self, shard_uid: ShardUId) -> usize {
+        self.tx_pools.get(&shard_uid).map_or(0, |pool| pool.len())
+    }
+
     /// Tries to insert the transaction into the pool for a given shard.
     pub fn insert_transaction(
         &mut self,
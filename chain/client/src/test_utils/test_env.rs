@@ -7,11 +7,13 @@ use near_async::time::Clock;
 use near_async::time::{Duration, Instant};
 use near_chain::test_utils::ValidatorSchedule;
 use near_chain::types::Tip;
-use near_chain::{ChainGenesis, Provenance};
+use near_chain::{ChainGenesis, ChainStoreAccess, Provenance};
 use near_chain_configs::GenesisConfig;
 use near_chain_primitives::error::QueryError;
 use near_chunks::client::ShardsManagerResponse;
 use near_chunks::test_utils::{MockClientAdapterForShardsManager, SynchronousShardsManagerAdapter};
+#[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+use near_crypto::PublicKey;
 use near_crypto::{InMemorySigner, KeyType, Signer};
 use near_network::client::ProcessTxResponse;
 use near_network::shards_manager::ShardsManagerRequestFromNetwork;
@@ -21,23 +23,35 @@ use near_network::types::PeerManagerMessageRequest;
 use near_network::types::{PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg};
 use near_o11y::testonly::TracingCapture;
 use near_parameters::RuntimeConfig;
+#[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+use near_primitives::account::AccessKey;
+use near_primitives::account::Account;
 use near_primitives::action::delegate::{DelegateAction, NonDelegateAction, SignedDelegateAction};
 use near_primitives::block::Block;
 use near_primitives::epoch_manager::RngSeed;
 use near_primitives::errors::InvalidTxError;
 use near_primitives::hash::CryptoHash;
+use near_primitives::receipt::ReceiptEnum;
 use near_primitives::sharding::{ChunkHash, PartialEncodedChunk};
 use near_primitives::stateless_validation::{ChunkEndorsement, ChunkStateWitness};
 use near_primitives::test_utils::create_test_signer;
 use near_primitives::transaction::{Action, FunctionCallAction, SignedTransaction};
-use near_primitives::types::{AccountId, Balance, BlockHeight, EpochId, NumSeats, ShardId};
+#[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+use near_primitives::transaction::{
+    AddKeyAction, CreateAccountAction, DeployContractAction, NonrefundableStorageTransferAction,
+};
+use near_primitives::trie_key::TrieKey;
+use near_primitives::types::{
+    AccountId, Balance, BlockHeight, BlockHeightDelta, BlockId, BlockReference, EpochId, Finality,
+    Gas, Nonce, NumSeats, ShardId, StorageUsage, SyncCheckpoint,
+};
 use near_primitives::utils::MaybeValidated;
 use near_primitives::version::ProtocolVersion;
 use near_primitives::views::{
-    AccountView, FinalExecutionOutcomeView, QueryRequest, QueryResponse, QueryResponseKind,
-    StateItem,
+    AccessKeyList, AccountView, ExecutionStatusView, FinalExecutionOutcomeView,
+    FinalExecutionStatus, QueryRequest, QueryResponse, QueryResponseKind, StateItem,
 };
-use near_store::ShardUId;
+use near_store::{ShardUId, Trie};
 use once_cell::sync::OnceCell;
 use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
@@ -66,6 +80,42 @@ pub struct TestEnv {
     pub(crate) seeds: HashMap<AccountId, RngSeed>,
     pub(crate) archive: bool,
     pub(crate) save_trie_changes: bool,
+    // total supply at the head before the last transaction submitted by `execute_tx`
+    pub(crate) last_tx_total_supply: Option<Balance>,
+}
+
+/// Tokens removed from the total supply by a transaction, by reason, see
+/// [`TestEnv::burn_breakdown`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct BurnBreakdown {
+    /// Balance burnt for gas, after refunds.
+    pub gas_burnt: Balance,
+    /// Non-refundable balance discarded when an account was deleted.
+    pub discarded_nonrefundable: Balance,
+}
+
+/// Parts of a receipt which do not depend on the blocks it was created in,
+/// see [`TestEnv::assert_deterministic_replay`].
+#[derive(Debug, PartialEq, Eq)]
+struct ReceiptShape {
+    predecessor_id: AccountId,
+    receiver_id: AccountId,
+    /// Actions of an action receipt, `None` for data receipts.
+    actions: Option<Vec<Action>>,
+}
+
+/// Composition of the balance of an account, see
+/// [`TestEnv::query_balance_breakdown`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BalanceBreakdown {
+    /// Balance the account can spend or transfer.
+    pub refundable: Balance,
+    /// Balance that can only pay for the storage of the account.
+    pub nonrefundable: Balance,
+    /// Balance staked by the account.
+    pub locked: Balance,
+    /// Sum of all of the above.
+    pub total: Balance,
 }
 
 pub struct StateWitnessPropagationOutput {
@@ -463,6 +513,13 @@ impl TestEnv {
         }
     }
 
+    /// Returns the protocol version of the epoch of the head of the first client.
+    pub fn current_protocol_version(&self) -> ProtocolVersion {
+        let client = &self.clients[0];
+        let epoch_id = client.chain.head().unwrap().epoch_id;
+        client.epoch_manager.get_epoch_protocol_version(&epoch_id).unwrap()
+    }
+
     pub fn query_account(&mut self, account_id: AccountId) -> AccountView {
         let client = &self.clients[0];
         let head = client.chain.head().unwrap();
@@ -504,13 +561,189 @@ impl TestEnv {
         panic!("No client tracks shard {}", shard_id);
     }
 
+    /// Returns the account as of the block in which the last receipt of the
+    /// given transaction executed on that account, regardless of later blocks.
+    pub fn account_view_after_tx(
+        &self,
+        account_id: AccountId,
+        tx_hash: &CryptoHash,
+    ) -> AccountView {
+        let client = &self.clients[0];
+        let outcome = client.chain.get_final_transaction_result(tx_hash).unwrap();
+        let block_hash = outcome
+            .receipts_outcome
+            .iter()
+            .rev()
+            .find(|receipt| receipt.outcome.executor_id == account_id)
+            .unwrap_or_else(|| panic!("transaction {tx_hash} did not execute on {account_id}"))
+            .block_hash;
+        let block = client.chain.get_block(&block_hash).unwrap();
+        let epoch_id = block.header().epoch_id();
+        let shard_id = client.epoch_manager.account_id_to_shard_id(&account_id, epoch_id).unwrap();
+        let shard_uid = client.epoch_manager.shard_id_to_uid(shard_id, epoch_id).unwrap();
+        let chunk_extra = client.chain.get_chunk_extra(&block_hash, &shard_uid).unwrap();
+        let response = client
+            .runtime_adapter
+            .query(
+                shard_uid,
+                chunk_extra.state_root(),
+                block.header().height(),
+                block.header().raw_timestamp(),
+                block.header().prev_hash(),
+                &block_hash,
+                epoch_id,
+                &QueryRequest::ViewAccount { account_id },
+            )
+            .unwrap();
+        match response.kind {
+            QueryResponseKind::ViewAccount(account_view) => account_view,
+            _ => panic!("Wrong return value"),
+        }
+    }
+
+    /// Returns all access keys of the account, with their nonces and
+    /// permissions, as of the state after the head block.
+    pub fn query_access_key_list(&self, account_id: AccountId) -> AccessKeyList {
+        let request = QueryRequest::ViewAccessKeyList { account_id: account_id.clone() };
+        match self.query_head_state(&account_id, &request) {
+            QueryResponseKind::AccessKeyList(access_key_list) => access_key_list,
+            _ => panic!("Wrong return value"),
+        }
+    }
+
+    /// Returns the account record as stored in the state after the head block,
+    /// including fields not exposed by `AccountView` such as its version.
+    pub fn query_account_raw(&self, account_id: AccountId) -> Account {
+        let request = QueryRequest::ViewAccountRaw { account_id: account_id.clone() };
+        match self.query_head_state(&account_id, &request) {
+            QueryResponseKind::AccountRaw(account) => account.record,
+            _ => panic!("Wrong return value"),
+        }
+    }
+
+    /// Runs the query against the state after the head block of the shard of
+    /// `account_id`, as seen by the first client.
+    fn query_head_state(
+        &self,
+        account_id: &AccountId,
+        request: &QueryRequest,
+    ) -> QueryResponseKind {
+        let client = &self.clients[0];
+        let head = client.chain.head().unwrap();
+        let block = client.chain.get_block(&head.last_block_hash).unwrap();
+        let shard_id =
+            client.epoch_manager.account_id_to_shard_id(account_id, &head.epoch_id).unwrap();
+        let shard_uid = client.epoch_manager.shard_id_to_uid(shard_id, &head.epoch_id).unwrap();
+        let chunk_extra = client.chain.get_chunk_extra(&head.last_block_hash, &shard_uid).unwrap();
+        client
+            .runtime_adapter
+            .query(
+                shard_uid,
+                chunk_extra.state_root(),
+                block.header().height(),
+                block.header().raw_timestamp(),
+                block.header().prev_hash(),
+                block.header().hash(),
+                block.header().epoch_id(),
+                request,
+            )
+            .unwrap()
+            .kind
+    }
+
+    /// View trie of the shard of `account_id` with the state after the head block.
+    fn view_trie_for_account(&self, account_id: &AccountId) -> Trie {
+        let client = &self.clients[0];
+        let head = client.chain.head().unwrap();
+        let shard_id =
+            client.epoch_manager.account_id_to_shard_id(account_id, &head.epoch_id).unwrap();
+        let shard_uid = client.epoch_manager.shard_id_to_uid(shard_id, &head.epoch_id).unwrap();
+        let chunk_extra = client.chain.get_chunk_extra(&head.last_block_hash, &shard_uid).unwrap();
+        client
+            .runtime_adapter
+            .get_view_trie_for_shard(shard_id, &head.last_block_hash, *chunk_extra.state_root())
+            .unwrap()
+    }
+
+    /// Asserts that the state after the head block holds no contract code of
+    /// `account_id`, e.g. because the account was deleted.
+    #[track_caller]
+    pub fn assert_code_removed(&self, account_id: &AccountId) {
+        let trie = self.view_trie_for_account(account_id);
+        let key = TrieKey::ContractCode { account_id: account_id.clone() };
+        assert!(
+            !trie.contains_key(&key.to_vec()).unwrap(),
+            "contract code of {account_id} is still in the state"
+        );
+    }
+
+    /// Asserts that all fields of the account are the same as in the `before` snapshot.
+    pub fn assert_account_unchanged(&mut self, account_id: AccountId, before: &AccountView) {
+        let after = self.query_account(account_id.clone());
+        assert_eq!(&after, before, "account {account_id} has changed");
+    }
+
+    /// Asserts that the transaction was rejected at admission and that its
+    /// signer was not charged, i.e. the signer account is the same as in the
+    /// `before` snapshot.
+    #[track_caller]
+    pub fn assert_no_gas_burnt_on_rejection(
+        &mut self,
+        result: &Result<FinalExecutionOutcomeView, InvalidTxError>,
+        signer_id: AccountId,
+        before: &AccountView,
+    ) {
+        assert!(result.is_err(), "transaction was not rejected: {result:?}");
+        self.assert_account_unchanged(signer_id, before);
+    }
+
+    /// Asserts that the transaction burnt gas, which is the case for failures
+    /// during execution, unlike for rejections at admission.
+    #[track_caller]
+    pub fn assert_gas_burnt_positive(&self, outcome: &FinalExecutionOutcomeView) {
+        let gas_burnt = outcome.transaction_outcome.outcome.gas_burnt
+            + outcome.receipts_outcome.iter().map(|r| r.outcome.gas_burnt).sum::<Gas>();
+        assert!(gas_burnt > 0, "no gas was burnt: {outcome:?}");
+        assert!(outcome.tokens_burnt() > 0, "no tokens were burnt: {outcome:?}");
+    }
+
+    /// Asserts that the nonce of the signer's access key is exactly one more
+    /// than `before`, as after a single applied transaction.
+    #[track_caller]
+    pub fn assert_nonce_advanced(&mut self, signer: &InMemorySigner, before: Nonce) {
+        let request = QueryRequest::ViewAccessKey {
+            account_id: signer.account_id.clone(),
+            public_key: signer.public_key.clone(),
+        };
+        let nonce = match self.query_view(request).unwrap().kind {
+            QueryResponseKind::AccessKey(view) => view.nonce,
+            _ => panic!("wrong query response"),
+        };
+        assert_eq!(nonce, before + 1, "nonce of {} did not advance by one", signer.account_id);
+    }
+
     /// Passes the given query to the runtime adapter using the current head and returns a result.
     pub fn query_view(&mut self, request: QueryRequest) -> Result<QueryResponse, QueryError> {
+        self.query_view_at(None, request)
+    }
+
+    /// Like `query_view`, but if `block_reference` is given, the query is
+    /// answered with the state after the referenced block instead. Returns an
+    /// error if the state of that block is already garbage collected.
+    pub fn query_view_at(
+        &mut self,
+        block_reference: Option<BlockReference>,
+        request: QueryRequest,
+    ) -> Result<QueryResponse, QueryError> {
+        if let Some(block_reference) = block_reference {
+            return self.query_view_at_block(&block_reference, &request);
+        }
         let head = self.clients[0].chain.head().unwrap();
         let head_block = self.clients[0].chain.get_block(&head.last_block_hash).unwrap();
+        let shard_uid = self.query_shard_uid(&head.epoch_id, &request);
         self.clients[0].runtime_adapter.query(
-            ShardUId::single_shard(),
-            &head_block.chunks()[0].prev_state_root(),
+            shard_uid,
+            &head_block.chunks()[shard_uid.shard_id() as usize].prev_state_root(),
             head.height,
             0,
             &head.prev_block_hash,
@@ -520,6 +753,92 @@ impl TestEnv {
         )
     }
 
+    fn query_view_at_block(
+        &self,
+        block_reference: &BlockReference,
+        request: &QueryRequest,
+    ) -> Result<QueryResponse, QueryError> {
+        let chain = &self.clients[0].chain;
+        let header = match block_reference {
+            BlockReference::BlockId(BlockId::Height(height)) => {
+                chain.get_block_header_by_height(*height)
+            }
+            BlockReference::BlockId(BlockId::Hash(hash)) => chain.get_block_header(hash),
+            BlockReference::Finality(Finality::Final) => {
+                chain.final_head().and_then(|tip| chain.get_block_header(&tip.last_block_hash))
+            }
+            BlockReference::Finality(_) => {
+                chain.head().and_then(|tip| chain.get_block_header(&tip.last_block_hash))
+            }
+            BlockReference::SyncCheckpoint(SyncCheckpoint::Genesis) => Ok(chain.genesis().clone()),
+            BlockReference::SyncCheckpoint(SyncCheckpoint::EarliestAvailable) => {
+                match chain.get_earliest_block_hash().unwrap() {
+                    Some(block_hash) => chain.get_block_header(&block_hash),
+                    None => Err(near_chain::Error::DBNotFoundErr(
+                        "no earliest available block".to_string(),
+                    )),
+                }
+            }
+        };
+        let header = match header {
+            Ok(header) => header,
+            Err(near_chain::Error::DBNotFoundErr(_)) => {
+                return Err(QueryError::UnknownBlock { block_reference: block_reference.clone() });
+            }
+            Err(err) => panic!("failed to look up block {block_reference:?}: {err}"),
+        };
+
+        let shard_uid = self.query_shard_uid(header.epoch_id(), request);
+        let chunk_extra = chain.get_chunk_extra(header.hash(), &shard_uid).map_err(|err| {
+            let head = chain.head().unwrap();
+            let gc_stop_height =
+                self.clients[0].runtime_adapter.get_gc_stop_height(&head.last_block_hash);
+            let error_message = if header.height() < gc_stop_height {
+                format!("state of block #{} is garbage collected", header.height())
+            } else {
+                err.to_string()
+            };
+            QueryError::InternalError {
+                error_message,
+                block_height: header.height(),
+                block_hash: *header.hash(),
+            }
+        })?;
+        self.clients[0].runtime_adapter.query(
+            shard_uid,
+            chunk_extra.state_root(),
+            header.height(),
+            header.raw_timestamp(),
+            header.prev_hash(),
+            header.hash(),
+            header.epoch_id(),
+            request,
+        )
+    }
+
+    /// Returns the shard that holds the state the query reads. A
+    /// `ViewAccounts` query is answered from the shard of its first account,
+    /// the runtime rejects it if the other accounts are on other shards.
+    fn query_shard_uid(&self, epoch_id: &EpochId, request: &QueryRequest) -> ShardUId {
+        let epoch_manager = &self.clients[0].epoch_manager;
+        let account_id = match request {
+            QueryRequest::ViewAccounts { account_ids } => account_ids.first(),
+            QueryRequest::ViewAccount { account_id }
+            | QueryRequest::ViewAccountRaw { account_id }
+            | QueryRequest::ViewAccountPendingActions { account_id }
+            | QueryRequest::ViewState { account_id, .. }
+            | QueryRequest::ViewAccessKey { account_id, .. }
+            | QueryRequest::ViewAccessKeyList { account_id }
+            | QueryRequest::CallFunction { account_id, .. }
+            | QueryRequest::ViewCode { account_id } => Some(account_id),
+        };
+        let shard_id = match account_id {
+            Some(account_id) => epoch_manager.account_id_to_shard_id(account_id, epoch_id).unwrap(),
+            None => epoch_manager.shard_ids(epoch_id).unwrap()[0],
+        };
+        epoch_manager.shard_id_to_uid(shard_id, epoch_id).unwrap()
+    }
+
     pub fn query_state(&mut self, account_id: AccountId) -> Vec<StateItem> {
         let client = &self.clients[0];
         let head = client.chain.head().unwrap();
@@ -555,6 +874,28 @@ impl TestEnv {
         self.query_account(account_id).amount
     }
 
+    /// Returns the refundable, non-refundable and locked balance of the
+    /// account together with their sum.
+    pub fn query_balance_breakdown(&mut self, account_id: AccountId) -> BalanceBreakdown {
+        let account = self.query_account(account_id);
+        #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+        let nonrefundable = account.nonrefundable;
+        #[cfg(not(feature = "protocol_feature_nonrefundable_transfer_nep491"))]
+        let nonrefundable = 0;
+        BalanceBreakdown {
+            refundable: account.amount,
+            nonrefundable,
+            locked: account.locked,
+            total: account.amount + nonrefundable + account.locked,
+        }
+    }
+
+    /// Number of bytes the state of the account occupies, including its
+    /// contract code.
+    pub fn query_storage_usage(&mut self, account_id: AccountId) -> StorageUsage {
+        self.query_account(account_id).storage_usage
+    }
+
     /// Restarts client at given index. Note that the new client reuses runtime
     /// adapter of old client.
     /// TODO (#8269): create new `KeyValueRuntime` for new client. Currently it
@@ -633,6 +974,59 @@ impl TestEnv {
         self.clients[idx].runtime_adapter.get_protocol_config(&epoch_id).unwrap().runtime_config
     }
 
+    /// Returns the actions of all delayed receipts targeting the given account,
+    /// in queue order, together with the id of the receipt they belong to.
+    ///
+    /// The delayed receipts are read from the state after the head block of
+    /// the first client.
+    pub fn query_pending_actions(&self, account_id: &AccountId) -> Vec<(CryptoHash, Action)> {
+        let request = QueryRequest::ViewAccountPendingActions { account_id: account_id.clone() };
+        match self.query_head_state(account_id, &request) {
+            QueryResponseKind::PendingActions(pending_actions) => pending_actions
+                .actions
+                .into_iter()
+                .map(|pending| (pending.receipt_id, pending.action.try_into().unwrap()))
+                .collect(),
+            _ => panic!("Wrong return value"),
+        }
+    }
+
+    /// Returns the gas used by applying the chunk of each shard in the given
+    /// block.
+    ///
+    /// The gas is read from the chunk extras, as the chunk headers only carry
+    /// the gas used by the chunk of the previous block.
+    pub fn block_gas_usage(&self, block_hash: &CryptoHash) -> Vec<(ShardId, Gas)> {
+        let client = &self.clients[0];
+        let epoch_id = client.chain.get_block_header(block_hash).unwrap().epoch_id().clone();
+        client
+            .epoch_manager
+            .shard_ids(&epoch_id)
+            .unwrap()
+            .into_iter()
+            .map(|shard_id| {
+                let shard_uid = client.epoch_manager.shard_id_to_uid(shard_id, &epoch_id).unwrap();
+                let chunk_extra = client.chain.get_chunk_extra(block_hash, &shard_uid).unwrap();
+                (shard_id, chunk_extra.gas_used())
+            })
+            .collect()
+    }
+
+    /// Returns the gas price in effect for the given block, i.e. the price
+    /// used to convert gas to tokens when applying its chunks.
+    ///
+    /// A header only records the price for the next block, so the price is
+    /// read from the header of the previous block. The genesis block has no
+    /// previous block, its price is the initial gas price of the genesis.
+    pub fn gas_price_at(&self, block_hash: &CryptoHash) -> Balance {
+        let chain = &self.clients[0].chain;
+        let header = chain.get_block_header(block_hash).unwrap();
+        if header.is_genesis() {
+            return header.next_gas_price();
+        }
+        chain.get_block_header(header.prev_hash()).unwrap().next_gas_price()
+    }
+
     /// Create and sign transaction ready for execution.
     pub fn tx_from_actions(
         &mut self,
@@ -689,21 +1083,43 @@ impl TestEnv {
         )
     }
 
-    /// Process a tx and its receipts, then return the execution outcome.
-    pub fn execute_tx(
-        &mut self,
-        tx: SignedTransaction,
-    ) -> Result<FinalExecutionOutcomeView, InvalidTxError> {
-        let tx_hash = tx.get_hash();
+    /// Submits a tx to the first client, recording the total supply before it.
+    fn submit_tx(&mut self, tx: SignedTransaction) -> Result<(), InvalidTxError> {
+        self.last_tx_total_supply =
+            Some(self.clients[0].chain.head_header().unwrap().total_supply());
         let response = self.clients[0].process_tx(tx, false, false);
         // Check if the transaction got rejected
         match response {
             ProcessTxResponse::NoResponse
             | ProcessTxResponse::RequestRouted
-            | ProcessTxResponse::ValidTx => (),
-            ProcessTxResponse::InvalidTx(e) => return Err(e),
+            | ProcessTxResponse::ValidTx => Ok(()),
+            ProcessTxResponse::InvalidTx(e) => Err(e),
             ProcessTxResponse::DoesNotTrackShard => panic!("test setup is buggy"),
         }
+    }
+
+    /// Adds a tx to the pool of the first client without producing any blocks.
+    /// Returns the error if the tx fails the validation done before admitting
+    /// it into the pool, which includes the protocol feature gating of its
+    /// actions.
+    pub fn try_add_transaction_to_pool(
+        &mut self,
+        tx: SignedTransaction,
+    ) -> Result<(), InvalidTxError> {
+        match self.clients[0].process_tx(tx, false, false) {
+            ProcessTxResponse::ValidTx => Ok(()),
+            ProcessTxResponse::InvalidTx(e) => Err(e),
+            response => panic!("transaction was neither added nor rejected: {response:?}"),
+        }
+    }
+
+    /// Process a tx and its receipts, then return the execution outcome.
+    pub fn execute_tx(
+        &mut self,
+        tx: SignedTransaction,
+    ) -> Result<FinalExecutionOutcomeView, InvalidTxError> {
+        let tx_hash = tx.get_hash();
+        self.submit_tx(tx)?;
         let max_iters = 100;
         let tip = self.clients[0].chain.head().unwrap();
         for i in 0..max_iters {
@@ -716,6 +1132,354 @@ impl TestEnv {
         panic!("No transaction outcome found after {max_iters} blocks.")
     }
 
+    /// Same as `execute_tx`, but keeps producing blocks until the transaction
+    /// and all receipts it spawned, on any shard, are executed and their
+    /// effects are visible to queries such as `query_account`.
+    pub fn execute_and_run_to_completion(
+        &mut self,
+        tx: SignedTransaction,
+    ) -> Result<FinalExecutionOutcomeView, InvalidTxError> {
+        self.execute_and_run_to_completion_with_max_blocks(tx, 100)
+    }
+
+    /// Same as `execute_and_run_to_completion`, but panics if the transaction
+    /// does not complete within `max_blocks` blocks.
+    pub fn execute_and_run_to_completion_with_max_blocks(
+        &mut self,
+        tx: SignedTransaction,
+        max_blocks: BlockHeightDelta,
+    ) -> Result<FinalExecutionOutcomeView, InvalidTxError> {
+        let tx_hash = tx.get_hash();
+        self.submit_tx(tx)?;
+        let max_height = self.clients[0].chain.head().unwrap().height + max_blocks;
+        loop {
+            let chain = &self.clients[0].chain;
+            if let Ok(outcome) = chain.get_final_transaction_result(&tx_hash) {
+                let resolved = !matches!(
+                    outcome.status,
+                    FinalExecutionStatus::NotStarted | FinalExecutionStatus::Started
+                );
+                if resolved && self.is_visible_to_queries(&outcome) {
+                    return Ok(outcome);
+                }
+            }
+            let height = chain.head().unwrap().height + 1;
+            assert!(height <= max_height, "{tx_hash} did not complete within {max_blocks} blocks");
+            let block = self.clients[0].produce_block(height).unwrap().unwrap();
+            self.process_block(0, block, Provenance::PRODUCED);
+        }
+    }
+
+    /// Same as `execute_and_run_to_completion`, but the blocks skip every
+    /// other height, so that none of them becomes final. Returns the outcome
+    /// as soon as the effects of the transaction are visible to queries, and a
+    /// competing fork built with `produce_blocks_on_fork` from the head at the
+    /// time of the call can still revert them.
+    pub fn execute_tx_without_finalizing(
+        &mut self,
+        tx: SignedTransaction,
+    ) -> Result<FinalExecutionOutcomeView, InvalidTxError> {
+        let tx_hash = tx.get_hash();
+        let head_height = self.clients[0].chain.head().unwrap().height;
+        self.submit_tx(tx)?;
+        let max_iters = 100;
+        for _ in 0..max_iters {
+            let chain = &self.clients[0].chain;
+            if let Ok(outcome) = chain.get_final_transaction_result(&tx_hash) {
+                let resolved = !matches!(
+                    outcome.status,
+                    FinalExecutionStatus::NotStarted | FinalExecutionStatus::Started
+                );
+                if resolved && self.is_visible_to_queries(&outcome) {
+                    let final_height = self.clients[0].chain.final_head().unwrap().height;
+                    assert!(
+                        final_height <= head_height,
+                        "blocks produced for {tx_hash} got finalized at height {final_height}"
+                    );
+                    return Ok(outcome);
+                }
+            }
+            let height = chain.head().unwrap().height + 2;
+            self.produce_block(0, height);
+        }
+        panic!("No transaction outcome found after {max_iters} blocks.")
+    }
+
+    /// Produces `count` blocks by the client with index 0 on top of
+    /// `from_hash`, at heights above the current head, and returns the hash of
+    /// the last one. Being higher than the current head, the fork becomes the
+    /// canonical chain.
+    ///
+    /// No chunks are produced while building the fork, so it only includes
+    /// chunks that were already ready for inclusion on top of `from_hash`. To
+    /// build a fork omitting a transaction, pass the hash of the head at the
+    /// time the transaction was submitted. Note that the reorg puts the
+    /// transactions of the abandoned chain back into the pool, so blocks
+    /// produced later on top of the fork may include them again.
+    pub fn produce_blocks_on_fork(&mut self, from_hash: CryptoHash, count: u64) -> CryptoHash {
+        let mut prev_hash = from_hash;
+        let mut height = self.clients[0].chain.head().unwrap().height;
+        for _ in 0..count {
+            height += 1;
+            let block = self.clients[0].produce_block_on(height, prev_hash).unwrap().unwrap();
+            prev_hash = *block.hash();
+            self.clients[0]
+                .process_block_test_no_produce_chunk(
+                    MaybeValidated::from(block),
+                    Provenance::PRODUCED,
+                )
+                .unwrap();
+        }
+        prev_hash
+    }
+
+    /// Whether the chunks of the head block on all shards are built on top of
+    /// the blocks of all outcomes, so queries, which read the state before the
+    /// chunks of the head block, see the effects of the outcomes.
+    fn is_visible_to_queries(&self, outcome: &FinalExecutionOutcomeView) -> bool {
+        let chain = &self.clients[0].chain;
+        let last_outcome_height = std::iter::once(&outcome.transaction_outcome)
+            .chain(&outcome.receipts_outcome)
+            .map(|outcome| chain.get_block_header(&outcome.block_hash).unwrap().height())
+            .max()
+            .unwrap();
+        let head = chain.head().unwrap();
+        let head_block = chain.get_block(&head.last_block_hash).unwrap();
+        head_block.chunks().iter().all(|chunk_header| {
+            let prev_block_header = chain.get_block_header(chunk_header.prev_block_hash()).unwrap();
+            prev_block_header.height() >= last_outcome_height
+        })
+    }
+
+    /// Submits a valid tx and returns the number of blocks that had to be
+    /// produced until its final outcome, including all receipts, was available.
+    pub fn blocks_to_resolve(&mut self, tx: SignedTransaction) -> u64 {
+        let tx_hash = tx.get_hash();
+        let response = self.clients[0].process_tx(tx, false, false);
+        assert_eq!(response, ProcessTxResponse::ValidTx);
+        let max_iters = 100;
+        let tip = self.clients[0].chain.head().unwrap();
+        for i in 1..=max_iters {
+            let block = self.clients[0].produce_block(tip.height + i).unwrap().unwrap();
+            self.process_block(0, block, Provenance::PRODUCED);
+            if let Ok(outcome) = self.clients[0].chain.get_final_transaction_result(&tx_hash) {
+                if matches!(
+                    outcome.status,
+                    FinalExecutionStatus::SuccessValue(_) | FinalExecutionStatus::Failure(_)
+                ) {
+                    return i;
+                }
+            }
+        }
+        panic!("No transaction outcome found after {max_iters} blocks.")
+    }
+
+    /// Executes the transaction both on this env and on `replay`, which must
+    /// be built independently from an identical genesis, and asserts that the
+    /// two executions produce the same outcome.
+    ///
+    /// Block hashes, proofs and receipt ids, which are derived from block
+    /// hashes and thus from timestamps, legitimately differ between the envs
+    /// and are normalized away before comparing. The transaction must refer to
+    /// a block known to both envs, e.g. the genesis block.
+    #[track_caller]
+    pub fn assert_deterministic_replay(&mut self, replay: &mut TestEnv, tx: SignedTransaction) {
+        let outcome = self.execute_and_run_to_completion(tx.clone()).unwrap();
+        let replayed_outcome = replay.execute_and_run_to_completion(tx).unwrap();
+        assert_eq!(
+            self.normalize_outcome(outcome),
+            replay.normalize_outcome(replayed_outcome),
+            "replaying the transaction produced a different outcome"
+        );
+    }
+
+    /// Strips the parts of the outcome that depend on the blocks it was
+    /// executed in and returns it with the shapes of its receipts.
+    fn normalize_outcome(
+        &self,
+        mut outcome: FinalExecutionOutcomeView,
+    ) -> (FinalExecutionOutcomeView, Vec<Option<ReceiptShape>>) {
+        let receipt_indices: HashMap<CryptoHash, u64> = outcome
+            .receipts_outcome
+            .iter()
+            .enumerate()
+            .map(|(index, receipt_outcome)| (receipt_outcome.id, index as u64))
+            .collect();
+        let normalize_id = |id: &mut CryptoHash| {
+            if let Some(index) = receipt_indices.get(id) {
+                *id = CryptoHash::hash_borsh(index);
+            }
+        };
+        let shapes = outcome
+            .receipts_outcome
+            .iter()
+            .map(|receipt_outcome| {
+                let receipt = self.clients[0]
+                    .chain
+                    .chain_store()
+                    .get_receipt(&receipt_outcome.id)
+                    .unwrap()?;
+                let actions = match &receipt.receipt {
+                    ReceiptEnum::Action(action_receipt)
+                    | ReceiptEnum::PromiseYield(action_receipt) => {
+                        Some(action_receipt.actions.clone())
+                    }
+                    ReceiptEnum::Data(_) | ReceiptEnum::PromiseResume(_) => None,
+                };
+                Some(ReceiptShape {
+                    predecessor_id: receipt.predecessor_id.clone(),
+                    receiver_id: receipt.receiver_id.clone(),
+                    actions,
+                })
+            })
+            .collect();
+        for execution_outcome in
+            std::iter::once(&mut outcome.transaction_outcome).chain(&mut outcome.receipts_outcome)
+        {
+            execution_outcome.proof.clear();
+            execution_outcome.block_hash = CryptoHash::default();
+            normalize_id(&mut execution_outcome.id);
+            execution_outcome.outcome.receipt_ids.iter_mut().for_each(&normalize_id);
+            if let ExecutionStatusView::SuccessReceiptId(id) = &mut execution_outcome.outcome.status
+            {
+                normalize_id(id);
+            }
+        }
+        (outcome, shapes)
+    }
+
+    /// Resubmits an already applied transaction and asserts that it is
+    /// rejected because of its nonce, so it cannot be applied twice.
+    #[track_caller]
+    pub fn assert_duplicate_rejected(&mut self, tx: SignedTransaction) {
+        let nonce = tx.transaction.nonce;
+        let response = self.clients[0].process_tx(tx, false, false);
+        assert!(
+            matches!(
+                response,
+                ProcessTxResponse::InvalidTx(InvalidTxError::InvalidNonce { tx_nonce, ak_nonce })
+                    if tx_nonce == nonce && ak_nonce >= nonce
+            ),
+            "duplicate transaction was not rejected: {response:?}",
+        );
+    }
+
+    /// Asserts that the total supply dropped by exactly `expected_burn +
+    /// expected_discard` since the last transaction was submitted with
+    /// `submit_tx`, which all the `execute_*` helpers use.
+    ///
+    /// `expected_burn` is the balance burnt for gas, `expected_discard` is the
+    /// balance removed from circulation otherwise, e.g. the non-refundable
+    /// balance of a deleted account. Rewards minted at the end of an epoch are
+    /// not accounted for, so the check must not cross an epoch boundary.
+    pub fn assert_supply_delta(&self, expected_burn: Balance, expected_discard: Balance) {
+        let supply_before =
+            self.last_tx_total_supply.expect("no transaction was submitted with `submit_tx`");
+        let supply_after = self.clients[0].chain.head_header().unwrap().total_supply();
+        assert_eq!(
+            supply_before.checked_sub(supply_after),
+            Some(expected_burn + expected_discard),
+            "total supply went from {supply_before} to {supply_after}, expected a burn of \
+             {expected_burn} and a discard of {expected_discard}"
+        );
+    }
+
+    /// Splits the tokens removed from the total supply by the transaction of
+    /// `outcome` into the gas burnt and the discarded non-refundable balance.
+    ///
+    /// The discarded balance is the non-refundable balance held, before the
+    /// block executing the receipt, by each account deleted by a successful
+    /// receipt of the transaction.
+    pub fn burn_breakdown(&self, outcome: &FinalExecutionOutcomeView) -> BurnBreakdown {
+        let chain = &self.clients[0].chain;
+        let discarded_nonrefundable = outcome
+            .receipts_outcome
+            .iter()
+            .filter(|receipt| !matches!(receipt.outcome.status, ExecutionStatusView::Failure(_)))
+            .filter(|receipt_outcome| {
+                let receipt =
+                    chain.chain_store().get_receipt(&receipt_outcome.id).unwrap().unwrap();
+                match &receipt.receipt {
+                    ReceiptEnum::Action(action_receipt)
+                    | ReceiptEnum::PromiseYield(action_receipt) => {
+                        action_receipt.actions.iter().any(|action| match action {
+                            Action::DeleteAccount(_) => true,
+                            #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+                            Action::DeleteAccountV2(_) => true,
+                            _ => false,
+                        })
+                    }
+                    ReceiptEnum::Data(_) | ReceiptEnum::PromiseResume(_) => false,
+                }
+            })
+            .map(|receipt_outcome| {
+                let header = chain.get_block_header(&receipt_outcome.block_hash).unwrap();
+                let block_reference = BlockReference::BlockId(BlockId::Hash(*header.prev_hash()));
+                let account_id = receipt_outcome.outcome.executor_id.clone();
+                let request = QueryRequest::ViewAccountRaw { account_id };
+                match self.query_view_at_block(&block_reference, &request) {
+                    Ok(QueryResponse { kind: QueryResponseKind::AccountRaw(account), .. }) => {
+                        account.record.nonrefundable()
+                    }
+                    // Created and deleted by the same receipt.
+                    Err(QueryError::UnknownAccount { .. }) => 0,
+                    response => panic!("unexpected response {response:?}"),
+                }
+            })
+            .sum();
+        BurnBreakdown { gas_burnt: outcome.tokens_burnt(), discarded_nonrefundable }
+    }
+
+    /// Creates `account_id` as a subaccount of the signer, sponsored with
+    /// `nonrefundable` balance only and optionally deploying `code` to it, and
+    /// returns the outcome of the creation.
+    ///
+    /// Asserts that the creation succeeded, that the new account holds no
+    /// balance besides the non-refundable deposit, that the deposit covers its
+    /// storage and that only the gas was burnt from the total supply.
+    #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+    pub fn create_and_verify_sponsored_account(
+        &mut self,
+        signer: &InMemorySigner,
+        account_id: AccountId,
+        nonrefundable: Balance,
+        code: Option<Vec<u8>>,
+    ) -> FinalExecutionOutcomeView {
+        let public_key = PublicKey::from_seed(KeyType::ED25519, account_id.as_str());
+        let mut actions = vec![
+            Action::CreateAccount(CreateAccountAction {}),
+            Action::AddKey(Box::new(AddKeyAction {
+                public_key,
+                access_key: AccessKey::full_access(),
+            })),
+            Action::NonrefundableStorageTransfer(NonrefundableStorageTransferAction {
+                deposit: nonrefundable,
+            }),
+        ];
+        if let Some(code) = code {
+            actions.push(Action::DeployContract(DeployContractAction { code }));
+        }
+        let tx = self.tx_from_actions(actions, signer, account_id.clone());
+        let outcome = self.execute_and_run_to_completion(tx).unwrap();
+        outcome.assert_success();
+
+        let account = self.query_account(account_id);
+        assert_eq!(account.amount, 0);
+        assert_eq!(account.nonrefundable, nonrefundable);
+        assert_eq!(account.locked, 0);
+        let epoch_id = self.clients[0].chain.head().unwrap().epoch_id;
+        let config = self.get_runtime_config(0, epoch_id);
+        let storage_cost = account.storage_usage as Balance * config.storage_amount_per_byte();
+        assert!(
+            account.nonrefundable >= storage_cost,
+            "storage of {} bytes is not covered by {} non-refundable balance",
+            account.storage_usage,
+            account.nonrefundable,
+        );
+        self.assert_supply_delta(outcome.tokens_burnt(), 0);
+        outcome
+    }
+
     /// Execute a function call transaction that calls main on the `TestEnv`.
     ///
     /// This function assumes that account has been deployed and that
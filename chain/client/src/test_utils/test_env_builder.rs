@@ -7,7 +7,7 @@ use near_async::messaging::{IntoMultiSender, IntoSender};
 use near_async::time::Clock;
 use near_chain::state_snapshot_actor::SnapshotCallbacks;
 use near_chain::test_utils::{KeyValueRuntime, MockEpochManager, ValidatorSchedule};
-use near_chain::types::RuntimeAdapter;
+use near_chain::types::{BlockEconomicsConfig, RuntimeAdapter};
 use near_chain::ChainGenesis;
 use near_chain_configs::GenesisConfig;
 use near_chunks::test_utils::MockClientAdapterForShardsManager;
@@ -16,7 +16,7 @@ use near_epoch_manager::{EpochManager, EpochManagerAdapter, EpochManagerHandle};
 use near_network::test_utils::MockPeerManagerAdapter;
 use near_parameters::RuntimeConfigStore;
 use near_primitives::epoch_manager::{AllEpochConfigTestOverrides, RngSeed};
-use near_primitives::types::{AccountId, NumShards};
+use near_primitives::types::{AccountId, Balance, NumShards};
 use near_store::config::StateSnapshotType;
 use near_store::test_utils::create_test_store;
 use near_store::{NodeStorage, ShardUId, Store, StoreConfig, TrieConfig};
@@ -58,6 +58,7 @@ pub struct TestEnvBuilder {
     archive: bool,
     save_trie_changes: bool,
     state_snapshot_enabled: bool,
+    gas_price: Option<Balance>,
 }
 
 /// Builder for the [`TestEnv`] structure.
@@ -86,6 +87,7 @@ impl TestEnvBuilder {
             archive: false,
             save_trie_changes: true,
             state_snapshot_enabled: false,
+            gas_price: None,
         }
     }
 
@@ -442,6 +444,18 @@ impl TestEnvBuilder {
         self
     }
 
+    /// Fixes the gas price of the genesis block and of all produced blocks,
+    /// instead of starting at the minimal gas price from the genesis config
+    /// and adjusting it to the gas used.
+    ///
+    /// Only works for genesis protocol versions which respect the gas price
+    /// limits of the genesis, see `BlockEconomicsConfig::min_gas_price`, and
+    /// until the protocol is upgraded. `build` panics otherwise.
+    pub fn gas_price(mut self, gas_price: Balance) -> Self {
+        self.gas_price = Some(gas_price);
+        self
+    }
+
     /// Constructs new `TestEnv` structure.
     ///
     /// If no clients were configured (either through count or vector) one
@@ -457,7 +471,22 @@ impl TestEnvBuilder {
 
     fn build_impl(self) -> TestEnv {
         let clock = self.clock.unwrap_or_else(|| Clock::real());
-        let chain_genesis = ChainGenesis::new(&self.genesis_config);
+        let mut chain_genesis = ChainGenesis::new(&self.genesis_config);
+        if let Some(gas_price) = self.gas_price {
+            chain_genesis.min_gas_price = gas_price;
+            chain_genesis.max_gas_price = gas_price;
+            let economics_config = BlockEconomicsConfig::from(&chain_genesis);
+            let protocol_version = chain_genesis.protocol_version;
+            let gas_price_range = (
+                economics_config.min_gas_price(protocol_version),
+                economics_config.max_gas_price(protocol_version),
+            );
+            assert_eq!(
+                gas_price_range,
+                (gas_price, gas_price),
+                "gas price override is ignored at genesis protocol version {protocol_version}"
+            );
+        }
         let clients = self.clients.clone();
         let num_clients = clients.len();
         let validators = self.validators;
@@ -555,6 +584,7 @@ impl TestEnvBuilder {
             seeds,
             archive: self.archive,
             save_trie_changes: self.save_trie_changes,
+            last_tx_total_supply: None,
         }
     }
 
@@ -54,13 +54,13 @@ use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{
     BlockView, ChunkView, EpochValidatorInfo, ExecutionOutcomeWithIdView, ExecutionStatusView,
     FinalExecutionOutcomeView, FinalExecutionOutcomeViewEnum, GasPriceView, LightClientBlockView,
-    MaintenanceWindowsView, QueryRequest, QueryResponse, ReceiptView, SplitStorageInfoView,
-    StateChangesKindsView, StateChangesView, TxExecutionStatus, TxStatusView,
+    MaintenanceWindowsView, QueryRequest, QueryResponse, QueryResponseKind, ReceiptView,
+    SplitStorageInfoView, StateChangesKindsView, StateChangesView, TxExecutionStatus, TxStatusView,
 };
 use near_store::flat::{FlatStorageReadyStatus, FlatStorageStatus};
 use near_store::{DBCol, COLD_HEAD_KEY, FINAL_HEAD_KEY, HEAD_KEY};
 use std::cmp::Ordering;
-use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 use std::hash::Hash;
 use std::sync::{Arc, Mutex, RwLock};
 use tracing::{error, info, warn};
@@ -327,7 +327,12 @@ impl ViewClientActor {
         }?;
 
         let account_id = match &msg.request {
+            QueryRequest::ViewAccounts { account_ids } => {
+                return self.query_accounts(&header, account_ids);
+            }
             QueryRequest::ViewAccount { account_id, .. } => account_id,
+            QueryRequest::ViewAccountRaw { account_id } => account_id,
+            QueryRequest::ViewAccountPendingActions { account_id } => account_id,
             QueryRequest::ViewState { account_id, .. } => account_id,
             QueryRequest::ViewAccessKey { account_id, .. } => account_id,
             QueryRequest::ViewAccessKeyList { account_id, .. } => account_id,
@@ -338,6 +343,62 @@ impl ViewClientActor {
             .epoch_manager
             .account_id_to_shard_id(account_id, header.epoch_id())
             .map_err(|err| QueryError::InternalError { error_message: err.to_string() })?;
+        self.query_shard(&header, shard_id, &msg.request)
+    }
+
+    /// Views the accounts with one runtime query per shard and returns them
+    /// in the order of `account_ids`.
+    fn query_accounts(
+        &self,
+        header: &BlockHeader,
+        account_ids: &[AccountId],
+    ) -> Result<QueryResponse, QueryError> {
+        let mut shard_account_ids: BTreeMap<ShardId, Vec<AccountId>> = BTreeMap::new();
+        for account_id in account_ids {
+            let shard_id = self
+                .epoch_manager
+                .account_id_to_shard_id(account_id, header.epoch_id())
+                .map_err(|err| QueryError::InternalError { error_message: err.to_string() })?;
+            shard_account_ids.entry(shard_id).or_default().push(account_id.clone());
+        }
+
+        let mut accounts = HashMap::new();
+        for (shard_id, account_ids) in shard_account_ids {
+            let request = QueryRequest::ViewAccounts { account_ids };
+            match self.query_shard(header, shard_id, &request)?.kind {
+                QueryResponseKind::Accounts(shard_accounts) => accounts.extend(
+                    shard_accounts.into_iter().map(|account| (account.account_id.clone(), account)),
+                ),
+                kind => {
+                    return Err(QueryError::Unreachable {
+                        error_message: format!("unexpected response to {request:?}: {kind:?}"),
+                    })
+                }
+            }
+        }
+
+        let accounts = account_ids
+            .iter()
+            .map(|account_id| {
+                accounts.get(account_id).cloned().ok_or_else(|| QueryError::Unreachable {
+                    error_message: format!("account {account_id} missing from the response"),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(QueryResponse {
+            kind: QueryResponseKind::Accounts(accounts),
+            block_height: header.height(),
+            block_hash: *header.hash(),
+        })
+    }
+
+    /// Runs the query against the state of the given shard at `header`.
+    fn query_shard(
+        &self,
+        header: &BlockHeader,
+        shard_id: ShardId,
+        request: &QueryRequest,
+    ) -> Result<QueryResponse, QueryError> {
         let shard_uid = self
             .epoch_manager
             .shard_id_to_uid(shard_id, header.epoch_id())
@@ -375,7 +436,7 @@ impl ViewClientActor {
             header.prev_hash(),
             header.hash(),
             header.epoch_id(),
-            &msg.request,
+            request,
         ) {
             Ok(query_response) => Ok(query_response),
             Err(query_error) => Err(match query_error {
@@ -421,6 +482,9 @@ impl ViewClientActor {
                     block_height,
                     block_hash,
                 },
+                near_chain::near_chain_primitives::error::QueryError::UnknownBlock {
+                    block_reference,
+                } => QueryError::UnknownBlock { block_reference },
             }),
         }
     }
@@ -81,6 +81,9 @@ pub enum QueryResponseKind {
     CallResult(near_primitives::views::CallResult),
     AccessKey(near_primitives::views::AccessKeyView),
     AccessKeyList(near_primitives::views::AccessKeyList),
+    Accounts(Vec<near_primitives::views::AccountViewResult>),
+    AccountRaw(near_primitives::views::AccountRawView),
+    PendingActions(near_primitives::views::PendingActionsView),
 }
 
 impl From<RpcQueryError> for crate::errors::RpcError {
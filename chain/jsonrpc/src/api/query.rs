@@ -167,6 +167,15 @@ impl RpcFrom<near_primitives::views::QueryResponseKind>
             near_primitives::views::QueryResponseKind::AccessKeyList(access_key_list) => {
                 Self::AccessKeyList(access_key_list)
             }
+            near_primitives::views::QueryResponseKind::Accounts(accounts) => {
+                Self::Accounts(accounts)
+            }
+            near_primitives::views::QueryResponseKind::AccountRaw(account) => {
+                Self::AccountRaw(account)
+            }
+            near_primitives::views::QueryResponseKind::PendingActions(actions) => {
+                Self::PendingActions(actions)
+            }
         }
     }
 }
@@ -333,8 +333,24 @@ impl JsonRpcHandler {
                     Ok(params) => params,
                     Err(err) => return (method_name, Err(RpcError::from(err))),
                 };
+                if matches!(
+                    params.request,
+                    QueryRequest::ViewAccountRaw { .. }
+                        | QueryRequest::ViewAccountPendingActions { .. }
+                ) && !self.enable_debug_rpc
+                {
+                    return (
+                        method_name,
+                        Err(RpcError::invalid_params("diagnostic queries require the debug RPC")),
+                    );
+                }
                 let metrics_name = match params.request {
                     QueryRequest::ViewAccount { .. } => "query_view_account",
+                    QueryRequest::ViewAccounts { .. } => "query_view_accounts",
+                    QueryRequest::ViewAccountRaw { .. } => "query_view_account_raw",
+                    QueryRequest::ViewAccountPendingActions { .. } => {
+                        "query_view_account_pending_actions"
+                    }
                     QueryRequest::ViewCode { .. } => "query_view_code",
                     QueryRequest::ViewState { include_proof, .. } => {
                         if include_proof {
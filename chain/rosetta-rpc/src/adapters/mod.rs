@@ -280,6 +280,42 @@ impl From<NearActions> for Vec<crate::models::Operation> {
                     );
                 }
 
+                #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+                // Strict deletion produces the same operations as a regular deletion.
+                // TODO(nonrefundable) Merge with the arm above on stabilization.
+                near_primitives::transaction::Action::DeleteAccountV2(action) => {
+                    let initiate_delete_account_operation_id =
+                        crate::models::OperationIdentifier::new(&operations);
+                    operations.push(
+                        validated_operations::InitiateDeleteAccountOperation {
+                            sender_account: sender_account_identifier.clone(),
+                        }
+                        .into_operation(initiate_delete_account_operation_id.clone()),
+                    );
+
+                    let delete_account_operation_id =
+                        crate::models::OperationIdentifier::new(&operations);
+                    operations.push(
+                        validated_operations::DeleteAccountOperation {
+                            account: receiver_account_identifier.clone(),
+                        }
+                        .into_related_operation(
+                            delete_account_operation_id.clone(),
+                            vec![initiate_delete_account_operation_id],
+                        ),
+                    );
+
+                    operations.push(
+                        validated_operations::RefundDeleteAccountOperation {
+                            beneficiary_account: action.beneficiary_id.into(),
+                        }
+                        .into_related_operation(
+                            crate::models::OperationIdentifier::new(&operations),
+                            vec![delete_account_operation_id],
+                        ),
+                    );
+                }
+
                 near_primitives::transaction::Action::AddKey(action) => {
                     let initiate_add_key_operation_id =
                         crate::models::OperationIdentifier::new(&operations);
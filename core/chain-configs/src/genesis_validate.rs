@@ -59,6 +59,18 @@ impl<'a> GenesisValidator<'a> {
                     self.validation_errors.push_genesis_semantics_error(error_message)
                 }
                 self.total_supply += account.locked() + account.amount() + account.nonrefundable();
+                #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+                if account.nonrefundable() > 0
+                    && self.genesis_config.protocol_version
+                        < near_primitives::version::ProtocolFeature::NonRefundableBalance
+                            .protocol_version()
+                {
+                    let error_message = format!(
+                        "account {} has non-refundable balance, which protocol version {} does not support",
+                        account_id, self.genesis_config.protocol_version
+                    );
+                    self.validation_errors.push_genesis_semantics_error(error_message)
+                }
                 self.account_ids.insert(account_id.clone());
                 if account.locked() > 0 {
                     self.staked_accounts.insert(account_id.clone(), account.locked());
@@ -212,6 +224,29 @@ mod test {
     #[test]
     fn test_total_supply_includes_nonrefundable_amount() {
         let mut config = GenesisConfig::default();
+        config.protocol_version = PROTOCOL_VERSION;
+        config.epoch_length = 42;
+        config.total_supply = 111;
+        config.validators = vec![AccountInfo {
+            account_id: "test".parse().unwrap(),
+            public_key: VALID_ED25519_RISTRETTO_KEY.parse().unwrap(),
+            amount: 10,
+        }];
+        let records = GenesisRecords(vec![StateRecord::Account {
+            account_id: "test".parse().unwrap(),
+            account: Account::new(100, 10, 1, Default::default(), 0, PROTOCOL_VERSION),
+        }]);
+        let genesis = &Genesis::new(config, records).unwrap();
+        validate_genesis(genesis).unwrap();
+    }
+
+    #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+    #[test]
+    #[should_panic(expected = "does not support")]
+    fn test_nonrefundable_amount_before_feature() {
+        let mut config = GenesisConfig::default();
+        config.protocol_version =
+            near_primitives::version::ProtocolFeature::NonRefundableBalance.protocol_version() - 1;
         config.epoch_length = 42;
         config.total_supply = 111;
         config.validators = vec![AccountInfo {
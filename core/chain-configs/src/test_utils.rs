@@ -8,8 +8,9 @@ use near_primitives::types::{
     AccountId, AccountInfo, Balance, BlockHeightDelta, NumSeats, NumShards,
 };
 use near_primitives::utils::{from_timestamp, generate_random_string};
-use near_primitives::version::PROTOCOL_VERSION;
+use near_primitives::version::{ProtocolVersion, PROTOCOL_VERSION};
 use num_rational::Ratio;
+use std::collections::HashMap;
 
 use crate::{
     Genesis, GenesisConfig, BLOCK_PRODUCER_KICKOUT_THRESHOLD, CHUNK_PRODUCER_KICKOUT_THRESHOLD,
@@ -116,6 +117,83 @@ impl Genesis {
         )
     }
 
+    /// Same as `test`, but starting the chain at `protocol_version`.
+    pub fn test_with_protocol_version(
+        accounts: Vec<AccountId>,
+        num_validator_seats: NumSeats,
+        protocol_version: ProtocolVersion,
+    ) -> Self {
+        let mut genesis = Self::test(accounts, num_validator_seats);
+        genesis.config.protocol_version = protocol_version;
+        genesis
+    }
+
+    /// Same as `test`, with an additional account holding only `nonrefundable`
+    /// non-refundable balance, as if it had been created by a sponsor. Its
+    /// full access key is derived from its id, like for the other accounts.
+    ///
+    /// Panics if the genesis protocol version does not support non-refundable
+    /// balance and `nonrefundable` is not zero.
+    pub fn test_with_sponsored_account(
+        accounts: Vec<AccountId>,
+        num_validator_seats: NumSeats,
+        sponsored_account_id: AccountId,
+        nonrefundable: Balance,
+    ) -> Self {
+        let mut genesis = Self::test(accounts, num_validator_seats);
+        let protocol_version = genesis.config.protocol_version;
+        let signer = InMemorySigner::from_seed(
+            sponsored_account_id.clone(),
+            KeyType::ED25519,
+            sponsored_account_id.as_ref(),
+        );
+        let records = genesis.force_read_records().as_mut();
+        records.push(StateRecord::Account {
+            account_id: sponsored_account_id.clone(),
+            account: Account::new(0, 0, nonrefundable, CryptoHash::default(), 0, protocol_version),
+        });
+        records.push(StateRecord::AccessKey {
+            account_id: sponsored_account_id,
+            public_key: signer.public_key,
+            access_key: AccessKey::full_access(),
+        });
+        genesis.config.total_supply += nonrefundable;
+        genesis
+    }
+
+    /// Same as `test` with a single validator, but the given accounts start
+    /// with the given regular and non-refundable balances respectively.
+    ///
+    /// Panics if the genesis protocol version does not support non-refundable
+    /// balance and some non-refundable balance is not zero.
+    pub fn test_with_balances(accounts: Vec<(AccountId, Balance, Balance)>) -> Self {
+        let account_ids = accounts.iter().map(|(account_id, _, _)| account_id.clone()).collect();
+        let mut genesis = Self::test(account_ids, 1);
+        let protocol_version = genesis.config.protocol_version;
+        let balances: HashMap<AccountId, (Balance, Balance)> = accounts
+            .into_iter()
+            .map(|(account_id, amount, nonrefundable)| (account_id, (amount, nonrefundable)))
+            .collect();
+        let records = genesis.force_read_records().as_mut();
+        for record in records.iter_mut() {
+            let StateRecord::Account { account_id, account } = record else {
+                continue;
+            };
+            if let Some(&(amount, nonrefundable)) = balances.get(account_id) {
+                *account = Account::new(
+                    amount,
+                    account.locked(),
+                    nonrefundable,
+                    account.code_hash(),
+                    account.storage_usage(),
+                    protocol_version,
+                );
+            }
+        }
+        genesis.config.total_supply = get_initial_supply(records);
+        genesis
+    }
+
     pub fn test_sharded(
         clock: Clock,
         accounts: Vec<AccountId>,
@@ -191,7 +269,7 @@ pub fn get_initial_supply(records: &[StateRecord]) -> Balance {
     let mut total_supply = 0;
     for record in records {
         if let StateRecord::Account { account, .. } = record {
-            total_supply += account.amount() + account.locked();
+            total_supply += account.amount() + account.locked() + account.nonrefundable();
         }
     }
     total_supply
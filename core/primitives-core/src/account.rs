@@ -660,4 +660,36 @@ mod tests {
             <Account as BorshDeserialize>::deserialize(&mut &serialized_account[..]).unwrap();
         assert_eq!(deserialized_account, account);
     }
+
+    /// Golden bytes of the V2 layout: sentinel, version byte, then the fields
+    /// of `AccountV2` in declaration order, with `nonrefundable` last.
+    #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+    #[test]
+    fn test_account_v2_borsh_golden_bytes() {
+        for nonrefundable in [0u128, 42] {
+            let account = Account {
+                amount: 1_000_000,
+                locked: 2_000_000,
+                nonrefundable,
+                code_hash: CryptoHash::default(),
+                storage_usage: 100,
+                version: AccountVersion::V2,
+            };
+            let expected = [
+                &[0xff; 16][..],
+                &[2],
+                &1_000_000u128.to_le_bytes(),
+                &2_000_000u128.to_le_bytes(),
+                &[0; 32],
+                &100u64.to_le_bytes(),
+                &nonrefundable.to_le_bytes(),
+            ]
+            .concat();
+            let serialized_account = borsh::to_vec(&account).unwrap();
+            assert_eq!(serialized_account, expected, "nonrefundable: {nonrefundable}");
+            let deserialized_account =
+                <Account as BorshDeserialize>::deserialize(&mut &expected[..]).unwrap();
+            assert_eq!(deserialized_account, account);
+        }
+    }
 }
@@ -7,7 +7,7 @@ use crate::types::ProtocolVersion;
 /// #[cfg(feature = "protocol_feature_evm")]
 /// EVM code
 ///
-#[derive(Hash, PartialEq, Eq, Clone, Copy, Debug)]
+#[derive(Hash, PartialEq, Eq, Clone, Copy, Debug, strum::EnumString)]
 pub enum ProtocolFeature {
     // stable features
     ImplicitAccountCreation,
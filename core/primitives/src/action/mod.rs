@@ -60,6 +60,27 @@ pub struct DeleteAccountAction {
     pub beneficiary_id: AccountId,
 }
 
+/// Like `DeleteAccountAction`, but lets the signer choose what happens to the
+/// non-refundable balance of the account.
+#[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    PartialEq,
+    Eq,
+    Clone,
+    Debug,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct DeleteAccountActionV2 {
+    pub beneficiary_id: AccountId,
+    /// Whether the deletion may burn the non-refundable balance of the
+    /// account. If false, deleting an account which still holds non-refundable
+    /// balance fails instead.
+    pub allow_nonrefundable_burn: bool,
+}
+
 #[derive(
     BorshSerialize,
     BorshDeserialize,
@@ -198,6 +219,10 @@ pub enum Action {
     /// Only possible during new account creation.
     /// For implicit account creation, it has to be the only action in the receipt.
     NonrefundableStorageTransfer(NonrefundableStorageTransferAction),
+    #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+    /// Deletes the account, failing instead of burning its non-refundable
+    /// balance unless explicitly allowed.
+    DeleteAccountV2(Box<DeleteAccountActionV2>),
 }
 
 const _: () = assert!(
@@ -280,3 +305,43 @@ impl From<DeleteAccountAction> for Action {
         Self::DeleteAccount(delete_account_action)
     }
 }
+
+#[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+impl From<DeleteAccountActionV2> for Action {
+    fn from(delete_account_action: DeleteAccountActionV2) -> Self {
+        Self::DeleteAccountV2(Box::new(delete_account_action))
+    }
+}
+
+#[cfg(all(test, feature = "protocol_feature_nonrefundable_transfer_nep491"))]
+mod tests {
+    use super::*;
+    use crate::test_utils::assert_borsh_bytes;
+
+    /// Borsh layout of the non-refundable transfer is part of the protocol.
+    #[test]
+    fn test_nonrefundable_storage_transfer_borsh_bytes() {
+        let action = NonrefundableStorageTransferAction { deposit: 1_000_000 };
+        let deposit_bytes = [0x40, 0x42, 0x0f, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert_borsh_bytes(&action, &deposit_bytes);
+
+        // The variant index has to stay the same as well.
+        let expected = [&[9u8][..], &deposit_bytes].concat();
+        assert_borsh_bytes(&Action::NonrefundableStorageTransfer(action), &expected);
+    }
+
+    /// Strict deletion is a separate variant so that `DeleteAccount` keeps its
+    /// layout.
+    #[test]
+    fn test_delete_account_v2_borsh_bytes() {
+        let action = DeleteAccountActionV2 {
+            beneficiary_id: "bob".parse().unwrap(),
+            allow_nonrefundable_burn: false,
+        };
+        let action_bytes = [3, 0, 0, 0, b'b', b'o', b'b', 0];
+        assert_borsh_bytes(&action, &action_bytes);
+
+        let expected = [&[10u8][..], &action_bytes].concat();
+        assert_borsh_bytes(&Action::DeleteAccountV2(Box::new(action)), &expected);
+    }
+}
@@ -4,6 +4,7 @@ use crate::types::{AccountId, Balance, EpochId, Gas, Nonce};
 use borsh::{BorshDeserialize, BorshSerialize};
 use near_crypto::PublicKey;
 use near_primitives_core::types::ProtocolVersion;
+use near_primitives_core::version::ProtocolFeature;
 use near_rpc_error_macro::RpcError;
 use std::fmt::{Debug, Display};
 
@@ -157,6 +158,11 @@ pub enum InvalidTxError {
         balance: Balance,
         #[serde(with = "dec_format")]
         cost: Balance,
+        /// Non-refundable balance of the signer. It is not part of `balance`,
+        /// since it can only pay for storage and not for transactions.
+        #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+        #[serde(with = "dec_format", default)]
+        nonrefundable_balance: Balance,
     },
     /// Signer account doesn't have enough balance after transaction.
     LackBalanceForState {
@@ -180,6 +186,19 @@ pub enum InvalidTxError {
 
 impl std::error::Error for InvalidTxError {}
 
+impl InvalidTxError {
+    /// Returns the protocol feature used by the transaction before it was
+    /// enabled, if the transaction was rejected for that reason.
+    pub fn gating_feature(&self) -> Option<ProtocolFeature> {
+        match self {
+            InvalidTxError::ActionsValidation(
+                ActionsValidationError::UnsupportedProtocolFeature { protocol_feature, .. },
+            ) => protocol_feature.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
 #[derive(
     BorshSerialize,
     BorshDeserialize,
@@ -259,6 +278,13 @@ pub enum ActionsValidationError {
     /// `ProtocolFeature` here because we don't want to leak the internals of
     /// that type into observable borsh serialization.
     UnsupportedProtocolFeature { protocol_feature: String, version: ProtocolVersion },
+    /// A non-refundable transfer to an implicit account can only create that
+    /// account, so it has to be the only action of the transaction.
+    #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+    NonrefundableTransferToImplicitAccountMustBeOnlyAction { receiver_id: AccountId },
+    /// The deposit of a non-refundable transfer has to be a positive number.
+    #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+    NonrefundableTransferAmountZero,
 }
 
 /// Describes the error for validating a receipt.
@@ -393,6 +419,17 @@ impl Display for ActionsValidationError {
                     protocol_feature,
                     version,
             ),
+            #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+            ActionsValidationError::NonrefundableTransferToImplicitAccountMustBeOnlyAction { receiver_id } => write!(
+                f,
+                "Non-refundable transfer to the implicit account {} must be the only action",
+                receiver_id,
+            ),
+            #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+            ActionsValidationError::NonrefundableTransferAmountZero => write!(
+                f,
+                "The deposit of a NonrefundableStorageTransfer action has to be a positive number",
+            ),
         }
     }
 }
@@ -510,6 +547,15 @@ pub enum ActionErrorKind {
     DelegateActionNonceTooLarge { delegate_nonce: Nonce, upper_bound: Nonce },
     /// Sending non-refundable balance to an existing account is not allowed according to NEP-491.
     NonRefundableBalanceToExistingAccount { account_id: AccountId },
+    /// Non-refundable balance is sent to an account which doesn't exist yet,
+    /// but is created by a later `CreateAccount` action of the same receipt.
+    /// The `CreateAccount` action has to come first.
+    #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+    NonRefundableBalanceBeforeAccountCreation { account_id: AccountId },
+    /// Deleting the account would burn its non-refundable balance, which the
+    /// `DeleteAccountV2` action does not allow.
+    #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+    CannotDeleteAccountWithNonrefundableBalance { account_id: AccountId, nonrefundable: Balance },
 }
 
 impl From<ActionErrorKind> for ActionError {
@@ -541,11 +587,33 @@ impl Display for InvalidTxError {
             InvalidTxError::InvalidSignature => {
                 write!(f, "Transaction is not signed with the given public key")
             }
+            #[cfg(not(feature = "protocol_feature_nonrefundable_transfer_nep491"))]
             InvalidTxError::NotEnoughBalance { signer_id, balance, cost } => write!(
                 f,
                 "Sender {:?} does not have enough balance {} for operation costing {}",
                 signer_id, balance, cost
             ),
+            #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+            InvalidTxError::NotEnoughBalance {
+                signer_id,
+                balance,
+                cost,
+                nonrefundable_balance,
+            } => {
+                write!(
+                    f,
+                    "Sender {:?} does not have enough balance {} for operation costing {}",
+                    signer_id, balance, cost
+                )?;
+                if *nonrefundable_balance > 0 {
+                    write!(
+                        f,
+                        "; its non-refundable balance {} can only be used for storage",
+                        nonrefundable_balance
+                    )?;
+                }
+                Ok(())
+            }
             InvalidTxError::LackBalanceForState { signer_id, amount } => {
                 write!(f, "Failed to execute, because the account {:?} wouldn't have enough balance to cover storage, required to have {} yoctoNEAR more", signer_id, amount)
             }
@@ -837,6 +905,14 @@ impl Display for ActionErrorKind {
             ActionErrorKind::NonRefundableBalanceToExistingAccount { account_id} => {
                 write!(f, "Can't send non-refundable balance to {} because it already exists", account_id)
             }
+            #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+            ActionErrorKind::NonRefundableBalanceBeforeAccountCreation { account_id } => {
+                write!(f, "Can't send non-refundable balance to {} before the CreateAccount action creating it", account_id)
+            }
+            #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+            ActionErrorKind::CannotDeleteAccountWithNonrefundableBalance { account_id, nonrefundable } => {
+                write!(f, "Can't delete account {} because it holds {} of non-refundable balance which the deletion is not allowed to burn", account_id, nonrefundable)
+            }
         }
     }
 }
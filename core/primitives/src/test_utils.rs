@@ -3,7 +3,7 @@ use crate::block::Block;
 use crate::block_body::{BlockBody, ChunkEndorsementSignatures};
 use crate::block_header::BlockHeader;
 use crate::challenge::Challenges;
-use crate::errors::EpochError;
+use crate::errors::{ActionError, ActionErrorKind, EpochError, TxExecutionError};
 use crate::hash::CryptoHash;
 use crate::merkle::PartialMerkleTree;
 use crate::num_rational::Ratio;
@@ -14,15 +14,18 @@ use crate::transaction::{
     TransferAction,
 };
 use crate::types::{AccountId, Balance, EpochId, EpochInfoProvider, Gas, Nonce};
+use crate::utils::{derive_eth_implicit_account_id, derive_near_implicit_account_id};
 use crate::validator_signer::{InMemoryValidatorSigner, ValidatorSigner};
 use crate::version::PROTOCOL_VERSION;
-use crate::views::{ExecutionStatusView, FinalExecutionOutcomeView, FinalExecutionStatus};
+use crate::views::{
+    ActionView, ExecutionStatusView, FinalExecutionOutcomeView, FinalExecutionStatus,
+};
 use near_async::time::Clock;
 use near_crypto::vrf::Value;
 use near_crypto::{EmptySigner, InMemorySigner, KeyType, PublicKey, SecretKey, Signature, Signer};
 use near_primitives_core::account::id::AccountIdRef;
 use near_primitives_core::types::ProtocolVersion;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 pub fn account_new(amount: Balance, code_hash: CryptoHash) -> Account {
@@ -667,6 +670,123 @@ pub fn eth_implicit_test_account() -> AccountId {
     "0x96791e923f8cf697ad9c3290f2c9059f0231b24c".parse().unwrap()
 }
 
+/// Checks that `value` is borsh-serialized to exactly `expected` and that
+/// `expected` deserializes back to `value`.
+///
+/// Use it with recorded bytes to catch accidental changes of the borsh layout
+/// of protocol types.
+#[track_caller]
+pub fn assert_borsh_bytes<T>(value: &T, expected: &[u8])
+where
+    T: borsh::BorshSerialize + borsh::BorshDeserialize + PartialEq + std::fmt::Debug,
+{
+    let bytes = borsh::to_vec(value).expect("borsh serialization failed");
+    assert_eq!(bytes, expected, "borsh layout of {value:?} has changed");
+    assert_eq!(&T::try_from_slice(expected).expect("borsh deserialization failed"), value);
+}
+
+/// Check that every outcome succeeded, i.e. its transaction and all transitive
+/// receipts have success status. Reports the indices of all failed outcomes.
+#[track_caller]
+pub fn assert_all_success(outcomes: &[FinalExecutionOutcomeView]) {
+    let failed: Vec<usize> = outcomes
+        .iter()
+        .enumerate()
+        .filter(|(_, outcome)| {
+            !matches!(outcome.status, FinalExecutionStatus::SuccessValue(_))
+                || outcome.receipts_outcome.iter().any(|receipt| {
+                    matches!(receipt.outcome.status, ExecutionStatusView::Failure(_))
+                })
+        })
+        .map(|(index, _)| index)
+        .collect();
+    assert!(failed.is_empty(), "outcomes {failed:?} of {} failed", outcomes.len());
+}
+
+/// Check that every outcome failed with an action error of the given kind.
+/// Reports the indices of all outcomes that did not.
+#[track_caller]
+pub fn assert_all_failed_with(outcomes: &[FinalExecutionOutcomeView], kind: &ActionErrorKind) {
+    let unexpected: Vec<(usize, &FinalExecutionStatus)> = outcomes
+        .iter()
+        .enumerate()
+        .filter(|(_, outcome)| {
+            !matches!(
+                &outcome.status,
+                FinalExecutionStatus::Failure(TxExecutionError::ActionError(ActionError {
+                    kind: actual,
+                    ..
+                })) if actual == kind
+            )
+        })
+        .map(|(index, outcome)| (index, &outcome.status))
+        .collect();
+    assert!(
+        unexpected.is_empty(),
+        "outcomes did not fail with {kind:?}, (index, status): {unexpected:?}",
+    );
+}
+
+/// Check that two outcomes describe the same behavior: same final status and,
+/// for every outcome in order, the same executor, logs, status and number of
+/// produced receipts. Gas, tokens burnt, ids and hashes are ignored, as they
+/// differ between runs.
+#[track_caller]
+pub fn assert_outcomes_equivalent_ignoring_gas(
+    left: &FinalExecutionOutcomeView,
+    right: &FinalExecutionOutcomeView,
+) {
+    fn normalize(
+        outcome: &FinalExecutionOutcomeView,
+    ) -> (FinalExecutionStatus, Vec<(AccountId, Vec<String>, ExecutionStatusView, usize)>) {
+        let outcomes = std::iter::once(&outcome.transaction_outcome)
+            .chain(&outcome.receipts_outcome)
+            .map(|outcome| {
+                let outcome = &outcome.outcome;
+                let status = match &outcome.status {
+                    ExecutionStatusView::SuccessReceiptId(_) => {
+                        ExecutionStatusView::SuccessReceiptId(CryptoHash::default())
+                    }
+                    status => status.clone(),
+                };
+                (
+                    outcome.executor_id.clone(),
+                    outcome.logs.clone(),
+                    status,
+                    outcome.receipt_ids.len(),
+                )
+            })
+            .collect();
+        (outcome.status.clone(), outcomes)
+    }
+    assert_eq!(normalize(left), normalize(right), "outcomes are not equivalent");
+}
+
+/// Runs `f` for each key type that controls an implicit account, together
+/// with the function deriving the implicit account id from a public key of
+/// that type.
+pub fn for_each_implicit_key_type(mut f: impl FnMut(KeyType, fn(&PublicKey) -> AccountId)) {
+    f(KeyType::ED25519, |public_key| {
+        derive_near_implicit_account_id(public_key.unwrap_as_ed25519())
+    });
+    f(KeyType::SECP256K1, |public_key| {
+        derive_eth_implicit_account_id(public_key.unwrap_as_secp256k1())
+    });
+}
+
+/// Signer of the implicit account controlled by the key derived from `seed`:
+/// a NEAR-implicit account for ED25519 keys and an ETH-implicit account for
+/// SECP256K1 keys.
+pub fn create_implicit_test_signer(key_type: KeyType, seed: &str) -> InMemorySigner {
+    let secret_key = SecretKey::from_seed(key_type, seed);
+    let public_key = secret_key.public_key();
+    let account_id = match &public_key {
+        PublicKey::ED25519(public_key) => derive_near_implicit_account_id(public_key),
+        PublicKey::SECP256K1(public_key) => derive_eth_implicit_account_id(public_key),
+    };
+    InMemorySigner::from_secret_key(account_id, secret_key)
+}
+
 impl FinalExecutionOutcomeView {
     #[track_caller]
     /// Check transaction and all transitive receipts for success status.
@@ -683,9 +803,115 @@ impl FinalExecutionOutcomeView {
         }
     }
 
+    #[track_caller]
+    /// Check that the transaction was converted into a receipt and that every
+    /// receipt in the outcome graph was executed successfully.
+    ///
+    /// Unlike `assert_success`, this also fails if the graph is incomplete,
+    /// i.e. some receipt produced by an outcome has no outcome of its own.
+    pub fn assert_all_actions_applied(&self) {
+        self.assert_success();
+        assert!(
+            matches!(
+                self.transaction_outcome.outcome.status,
+                ExecutionStatusView::SuccessReceiptId(_)
+            ),
+            "transaction was not converted to a receipt: {:?}",
+            self.transaction_outcome,
+        );
+        let executed: HashSet<CryptoHash> =
+            self.receipts_outcome.iter().map(|outcome| outcome.id).collect();
+        for outcome in std::iter::once(&self.transaction_outcome).chain(&self.receipts_outcome) {
+            for receipt_id in &outcome.outcome.receipt_ids {
+                assert!(executed.contains(receipt_id), "receipt {receipt_id} has no outcome");
+            }
+        }
+    }
+
+    #[track_caller]
+    /// Check that the receipt ids referenced by the outcomes form a DAG rooted
+    /// at the transaction: every referenced receipt has an outcome and no
+    /// receipt transitively produces itself.
+    pub fn assert_valid_receipt_dag(&self) {
+        let receipt_ids: HashMap<CryptoHash, &[CryptoHash]> =
+            std::iter::once(&self.transaction_outcome)
+                .chain(&self.receipts_outcome)
+                .map(|outcome| (outcome.id, outcome.outcome.receipt_ids.as_slice()))
+                .collect();
+        for (id, children) in &receipt_ids {
+            for child in children.iter() {
+                assert!(
+                    receipt_ids.contains_key(child),
+                    "receipt {child} produced by {id} has no outcome"
+                );
+            }
+        }
+
+        // Depth-first search, a node met again while on the current path
+        // closes a cycle.
+        let mut on_path = HashSet::new();
+        let mut finished = HashSet::new();
+        let mut stack = vec![(self.transaction_outcome.id, false)];
+        while let Some((id, exiting)) = stack.pop() {
+            if exiting {
+                on_path.remove(&id);
+                finished.insert(id);
+                continue;
+            }
+            if finished.contains(&id) {
+                continue;
+            }
+            assert!(on_path.insert(id), "receipt {id} is part of a cycle");
+            stack.push((id, true));
+            for child in receipt_ids[&id] {
+                assert!(!on_path.contains(child), "receipt {child} is part of a cycle");
+                stack.push((*child, false));
+            }
+        }
+    }
+
+    #[track_caller]
+    /// Check that the logs of the transaction and all receipts, flattened in
+    /// the order of their outcomes, are exactly `expected`.
+    pub fn assert_logs_eq(&self, expected: &[&str]) {
+        let logs: Vec<&str> = std::iter::once(&self.transaction_outcome)
+            .chain(&self.receipts_outcome)
+            .flat_map(|outcome| outcome.outcome.logs.iter().map(String::as_str))
+            .collect();
+        assert_eq!(logs, expected, "unexpected logs, left: actual, right: expected");
+    }
+
     /// Calculates how much NEAR was burnt for gas, after refunds.
     pub fn tokens_burnt(&self) -> Balance {
         self.transaction_outcome.outcome.tokens_burnt
             + self.receipts_outcome.iter().map(|r| r.outcome.tokens_burnt).sum::<u128>()
     }
+
+    /// Calculates the net change of the signer's balance caused by the
+    /// transaction, i.e. the deposits it transferred away and the gas it paid
+    /// for after refunds.
+    ///
+    /// Deposits count only if the transaction succeeded, as they are refunded
+    /// otherwise, and only if the signer is not the receiver.
+    pub fn sender_balance_delta(&self) -> i128 {
+        let transferred = matches!(self.status, FinalExecutionStatus::SuccessValue(_))
+            && self.transaction.signer_id != self.transaction.receiver_id;
+        let deposits: Balance = if transferred {
+            self.transaction
+                .actions
+                .iter()
+                .map(|action| match action {
+                    ActionView::FunctionCall { deposit, .. } | ActionView::Transfer { deposit } => {
+                        *deposit
+                    }
+                    #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+                    ActionView::NonrefundableStorageTransfer { deposit } => *deposit,
+                    _ => 0,
+                })
+                .sum()
+        } else {
+            0
+        };
+        -i128::try_from(deposits + self.tokens_burnt()).unwrap()
+    }
 }
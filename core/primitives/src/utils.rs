@@ -9,7 +9,7 @@ use rand::{thread_rng, Rng};
 use serde;
 
 use crate::hash::{hash, CryptoHash};
-use crate::transaction::SignedTransaction;
+use crate::transaction::{Action, SignedTransaction};
 use crate::types::{NumSeats, NumShards, ShardId};
 use crate::version::{
     ProtocolVersion, CORRECT_RANDOM_VALUE_PROTOCOL_VERSION, CREATE_HASH_PROTOCOL_VERSION,
@@ -480,6 +480,24 @@ pub fn account_is_implicit(account_id: &AccountId, eth_implicit_accounts_enabled
     }
 }
 
+/// Returns whether applying `actions` to `receiver_id` creates an account,
+/// provided that the receiver does not exist yet.
+///
+/// That is the case for an explicit `CreateAccount` action, and for an
+/// implicit account receiver funded by a transfer that is the only action.
+pub fn actions_create_account(receiver_id: &AccountId, actions: &[Action]) -> bool {
+    if actions.iter().any(|action| matches!(action, Action::CreateAccount(_))) {
+        return true;
+    }
+    let funds_implicit_account = match actions {
+        [Action::Transfer(_)] => true,
+        #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+        [Action::NonrefundableStorageTransfer(_)] => true,
+        _ => false,
+    };
+    funds_implicit_account && receiver_id.get_account_type().is_implicit()
+}
+
 /// Returns hex-encoded copy of the public key.
 /// This is a NEAR-implicit account ID which can be controlled by the corresponding ED25519 private key.
 pub fn derive_near_implicit_account_id(public_key: &ED25519PublicKey) -> AccountId {
@@ -516,6 +534,62 @@ mod tests {
         assert_eq!(account_id, expected);
     }
 
+    #[test]
+    fn test_implicit_signer_from_seed() {
+        use crate::test_utils::create_implicit_test_signer;
+        use near_crypto::Signer;
+
+        let signer = create_implicit_test_signer(KeyType::ED25519, "test");
+        let public_key = signer.public_key();
+        assert_eq!(
+            signer.account_id,
+            derive_near_implicit_account_id(public_key.unwrap_as_ed25519())
+        );
+
+        let signer = create_implicit_test_signer(KeyType::SECP256K1, "test");
+        let public_key = signer.public_key();
+        assert_eq!(
+            signer.account_id,
+            derive_eth_implicit_account_id(public_key.unwrap_as_secp256k1())
+        );
+    }
+
+    #[test]
+    fn test_actions_create_account() {
+        use crate::account::AccessKey;
+        use crate::action::{AddKeyAction, CreateAccountAction, TransferAction};
+
+        let named: AccountId = "alice.near".parse().unwrap();
+        let public_key = PublicKey::from_seed(KeyType::ED25519, "test");
+        let implicit = derive_near_implicit_account_id(public_key.unwrap_as_ed25519());
+        let transfer = Action::Transfer(TransferAction { deposit: 1 });
+
+        let named_creation = [
+            Action::CreateAccount(CreateAccountAction {}),
+            Action::AddKey(Box::new(AddKeyAction {
+                public_key,
+                access_key: AccessKey::full_access(),
+            })),
+            transfer.clone(),
+        ];
+        assert!(actions_create_account(&named, &named_creation));
+
+        assert!(actions_create_account(&implicit, &[transfer.clone()]));
+        #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+        {
+            use crate::action::NonrefundableStorageTransferAction;
+            let nonrefundable_transfer =
+                Action::NonrefundableStorageTransfer(NonrefundableStorageTransferAction {
+                    deposit: 1,
+                });
+            assert!(actions_create_account(&implicit, &[nonrefundable_transfer]));
+        }
+        // Implicit accounts are only created by a lone transfer.
+        assert!(!actions_create_account(&implicit, &[transfer.clone(), transfer.clone()]));
+
+        assert!(!actions_create_account(&named, &[transfer]));
+    }
+
     #[test]
     fn test_num_chunk_producers() {
         for num_seats in 1..50 {
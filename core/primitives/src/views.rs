@@ -23,14 +23,14 @@ use crate::sharding::{
     ChunkHash, ShardChunk, ShardChunkHeader, ShardChunkHeaderInner, ShardChunkHeaderInnerV2,
     ShardChunkHeaderV3,
 };
-#[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
-use crate::transaction::NonrefundableStorageTransferAction;
 use crate::transaction::{
     Action, AddKeyAction, CreateAccountAction, DeleteAccountAction, DeleteKeyAction,
     DeployContractAction, ExecutionMetadata, ExecutionOutcome, ExecutionOutcomeWithIdAndProof,
     ExecutionStatus, FunctionCallAction, PartialExecutionOutcome, PartialExecutionStatus,
     SignedTransaction, StakeAction, TransferAction,
 };
+#[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+use crate::transaction::{DeleteAccountActionV2, NonrefundableStorageTransferAction};
 use crate::types::{
     AccountId, AccountWithPublicKey, Balance, BlockHeight, EpochHeight, EpochId, FunctionArgs, Gas,
     Nonce, NumBlocks, ShardId, StateChangeCause, StateChangeKind, StateChangeValue,
@@ -54,13 +54,20 @@ use strum::IntoEnumIterator;
 use validator_stake_view::ValidatorStakeView;
 
 /// A view of the account
-#[derive(serde::Serialize, serde::Deserialize, Debug, Eq, PartialEq, Clone)]
+///
+/// Besides its fields, the JSON form of the view contains the `total_balance`
+/// of the account, see `AccountView::total_balance`.
+#[derive(serde::Deserialize, Debug, Eq, PartialEq, Clone)]
 pub struct AccountView {
+    /// Liquid balance, the only balance the account owner can transfer.
     #[serde(with = "dec_format")]
     pub amount: Balance,
     #[serde(with = "dec_format")]
     pub locked: Balance,
-    #[serde(with = "dec_format")]
+    /// Balance given by a sponsor to pay for storage, which can't be
+    /// transferred. Zero for accounts that were never sponsored, and defaults
+    /// to zero when missing for compatibility with older nodes.
+    #[serde(with = "dec_format", default)]
     #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
     pub nonrefundable: Balance,
     pub code_hash: CryptoHash,
@@ -70,6 +77,94 @@ pub struct AccountView {
     pub storage_paid_at: BlockHeight,
 }
 
+impl AccountView {
+    /// Whether a contract is deployed on the account.
+    pub fn has_contract(&self) -> bool {
+        self.code_hash != CryptoHash::default()
+    }
+
+    /// All balance of the account, including the locked and the
+    /// non-refundable balance, as opposed to the refundable `amount`.
+    pub fn total_balance(&self) -> Balance {
+        let total = self.amount + self.locked;
+        #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+        let total = total + self.nonrefundable;
+        total
+    }
+}
+
+impl serde::Serialize for AccountView {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        #[derive(serde::Serialize)]
+        struct SerializedAccountView<'a> {
+            #[serde(with = "dec_format")]
+            amount: Balance,
+            #[serde(with = "dec_format")]
+            locked: Balance,
+            #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+            #[serde(with = "dec_format")]
+            nonrefundable: Balance,
+            /// Lets wallets tell the sponsored storage apart from the
+            /// refundable `amount` without summing the balances themselves.
+            #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+            #[serde(with = "dec_format")]
+            total_balance: Balance,
+            code_hash: &'a CryptoHash,
+            storage_usage: StorageUsage,
+            storage_paid_at: BlockHeight,
+        }
+
+        let view = SerializedAccountView {
+            amount: self.amount,
+            locked: self.locked,
+            #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+            nonrefundable: self.nonrefundable,
+            #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+            total_balance: self.total_balance(),
+            code_hash: &self.code_hash,
+            storage_usage: self.storage_usage,
+            storage_paid_at: self.storage_paid_at,
+        };
+        serde::Serialize::serialize(&view, serializer)
+    }
+}
+
+/// One entry of the response to `QueryRequest::ViewAccounts`.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct AccountViewResult {
+    pub account_id: AccountId,
+    /// The account, or `None` if it could not be viewed.
+    #[serde(default)]
+    pub account: Option<AccountView>,
+    /// Why the account could not be viewed, set iff `account` is `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Response to `QueryRequest::ViewAccountRaw`.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct AccountRawView {
+    /// The account as stored in the state, including its version.
+    pub record: Account,
+}
+
+/// Response to `QueryRequest::ViewAccountPendingActions`.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct PendingActionsView {
+    /// Actions in the order in which the receipts wait in the queue.
+    pub actions: Vec<PendingActionView>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct PendingActionView {
+    /// Id of the delayed receipt the action belongs to.
+    pub receipt_id: CryptoHash,
+    pub action: ActionView,
+}
+
 /// A view of the contract code.
 #[serde_as]
 #[derive(serde::Serialize, serde::Deserialize, PartialEq, Eq, Debug, Clone)]
@@ -302,6 +397,10 @@ pub enum QueryResponseKind {
     CallResult(CallResult),
     AccessKey(AccessKeyView),
     AccessKeyList(AccessKeyList),
+    /// Accounts in the order of `QueryRequest::ViewAccounts::account_ids`.
+    Accounts(Vec<AccountViewResult>),
+    AccountRaw(AccountRawView),
+    PendingActions(PendingActionsView),
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
@@ -310,6 +409,24 @@ pub enum QueryRequest {
     ViewAccount {
         account_id: AccountId,
     },
+    /// Views several accounts at once, possibly from different shards.
+    /// Accounts that can't be viewed are reported per entry instead of
+    /// failing the whole request.
+    ViewAccounts {
+        account_ids: Vec<AccountId>,
+    },
+    /// Views the account record as stored in the state, including internal
+    /// fields such as its version. Meant for diagnostics, so only served by
+    /// the RPC when its debug endpoints are enabled.
+    ViewAccountRaw {
+        account_id: AccountId,
+    },
+    /// Lists the actions of the delayed receipts targeting the account. Meant
+    /// for diagnostics, so only served by the RPC when its debug endpoints
+    /// are enabled.
+    ViewAccountPendingActions {
+        account_id: AccountId,
+    },
     ViewCode {
         account_id: AccountId,
     },
@@ -1219,6 +1336,11 @@ pub enum ActionView {
         delegate_action: DelegateAction,
         signature: Signature,
     },
+    #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+    DeleteAccountV2 {
+        beneficiary_id: AccountId,
+        allow_nonrefundable_burn: bool,
+    },
 }
 
 impl From<Action> for ActionView {
@@ -1255,6 +1377,11 @@ impl From<Action> for ActionView {
                 delegate_action: action.delegate_action,
                 signature: action.signature,
             },
+            #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+            Action::DeleteAccountV2(action) => ActionView::DeleteAccountV2 {
+                beneficiary_id: action.beneficiary_id,
+                allow_nonrefundable_burn: action.allow_nonrefundable_burn,
+            },
         }
     }
 }
@@ -1296,6 +1423,13 @@ impl TryFrom<ActionView> for Action {
             ActionView::Delegate { delegate_action, signature } => {
                 Action::Delegate(Box::new(SignedDelegateAction { delegate_action, signature }))
             }
+            #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+            ActionView::DeleteAccountV2 { beneficiary_id, allow_nonrefundable_burn } => {
+                Action::DeleteAccountV2(Box::new(DeleteAccountActionV2 {
+                    beneficiary_id,
+                    allow_nonrefundable_burn,
+                }))
+            }
         })
     }
 }
@@ -1987,6 +2121,87 @@ impl From<Receipt> for ReceiptView {
     }
 }
 
+/// Balance returned to an account by a refund receipt, see [`ReceiptView::refund`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RefundView {
+    pub receiver_id: AccountId,
+    pub amount: Balance,
+    /// Whether unused gas is refunded, as opposed to a deposit.
+    pub is_gas_refund: bool,
+}
+
+impl ReceiptView {
+    /// Returns the refund carried by the receipt if it is a refund created by
+    /// the runtime, see `Receipt::new_balance_refund` and `Receipt::new_gas_refund`.
+    pub fn refund(&self) -> Option<RefundView> {
+        if !self.predecessor_id.is_system() {
+            return None;
+        }
+        match &self.receipt {
+            ReceiptEnumView::Action { signer_id, actions, .. } => match actions.as_slice() {
+                [ActionView::Transfer { deposit }] => Some(RefundView {
+                    receiver_id: self.receiver_id.clone(),
+                    amount: *deposit,
+                    is_gas_refund: signer_id == &self.receiver_id,
+                }),
+                _ => None,
+            },
+            ReceiptEnumView::Data { .. } => None,
+        }
+    }
+
+    /// Returns the receiver of the receipt if executing the receipt with
+    /// `status` created it. That is the case for a successful receipt starting
+    /// with `CreateAccount` or containing a non-refundable transfer, which is
+    /// rejected for existing accounts.
+    ///
+    /// An implicit account created by a regular transfer is not reported, as
+    /// the receipt is the same as for a transfer to an existing account.
+    pub fn created_account_id(&self, status: &ExecutionStatusView) -> Option<&AccountId> {
+        if !matches!(
+            status,
+            ExecutionStatusView::SuccessValue(_) | ExecutionStatusView::SuccessReceiptId(_)
+        ) {
+            return None;
+        }
+        let actions = match &self.receipt {
+            ReceiptEnumView::Action { actions, .. } => actions,
+            ReceiptEnumView::Data { .. } => return None,
+        };
+        let starts_with_create_account = matches!(actions.first(), Some(ActionView::CreateAccount));
+        #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+        let has_nonrefundable_transfer = actions
+            .iter()
+            .any(|action| matches!(action, ActionView::NonrefundableStorageTransfer { .. }));
+        #[cfg(not(feature = "protocol_feature_nonrefundable_transfer_nep491"))]
+        let has_nonrefundable_transfer = false;
+        (starts_with_create_account || has_nonrefundable_transfer).then_some(&self.receiver_id)
+    }
+}
+
+impl FinalExecutionOutcomeWithReceiptView {
+    /// Refunds among the receipts generated by the transaction.
+    pub fn refunds(&self) -> Vec<RefundView> {
+        self.receipts.iter().filter_map(ReceiptView::refund).collect()
+    }
+
+    /// Accounts created by the receipts generated by the transaction, see
+    /// `ReceiptView::created_account_id`.
+    pub fn created_account_ids(&self) -> Vec<AccountId> {
+        self.receipts
+            .iter()
+            .filter_map(|receipt| {
+                let outcome = self
+                    .final_outcome
+                    .receipts_outcome
+                    .iter()
+                    .find(|outcome| outcome.id == receipt.receipt_id)?;
+                receipt.created_account_id(&outcome.outcome.status).cloned()
+            })
+            .collect()
+    }
+}
+
 impl TryFrom<ReceiptView> for Receipt {
     type Error = Box<dyn std::error::Error + Send + Sync>;
 
@@ -2421,6 +2636,32 @@ mod tests {
         insta::assert_json_snapshot!(&view, { ".wasm_config.vm_kind" => "<REDACTED>"});
     }
 
+    #[test]
+    fn test_receipt_view_refund() {
+        use super::{ReceiptView, RefundView};
+        use crate::receipt::Receipt;
+        use near_crypto::{KeyType, PublicKey};
+
+        let alice: crate::types::AccountId = "alice.near".parse().unwrap();
+        let balance_refund = ReceiptView::from(Receipt::new_balance_refund(&alice, 10));
+        assert_eq!(
+            balance_refund.refund(),
+            Some(RefundView { receiver_id: alice.clone(), amount: 10, is_gas_refund: false })
+        );
+        let gas_refund = ReceiptView::from(Receipt::new_gas_refund(
+            &alice,
+            20,
+            PublicKey::empty(KeyType::ED25519),
+        ));
+        assert_eq!(
+            gas_refund.refund(),
+            Some(RefundView { receiver_id: alice.clone(), amount: 20, is_gas_refund: true })
+        );
+        let mut transfer = Receipt::new_balance_refund(&alice, 10);
+        transfer.predecessor_id = "bob.near".parse().unwrap();
+        assert_eq!(ReceiptView::from(transfer).refund(), None);
+    }
+
     /// `ExecutionMetadataView` with profile V1 displayed on the RPC should not change.
     #[test]
     fn test_exec_metadata_v1_view() {
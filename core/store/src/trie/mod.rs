@@ -25,7 +25,7 @@ use near_primitives::hash::{hash, CryptoHash};
 pub use near_primitives::shard_layout::ShardUId;
 use near_primitives::state::{FlatStateValue, ValueRef};
 use near_primitives::state_record::StateRecord;
-use near_primitives::trie_key::trie_key_parsers::parse_account_id_prefix;
+use near_primitives::trie_key::trie_key_parsers::{self, parse_account_id_prefix};
 use near_primitives::trie_key::TrieKey;
 use near_primitives::types::{AccountId, StateRoot, StateRootNode};
 use near_vm_runner::ContractCode;
@@ -1542,6 +1542,38 @@ impl Trie {
     pub fn get_trie_nodes_count(&self) -> TrieNodesCount {
         self.accounting_cache.borrow().get_trie_nodes_count()
     }
+
+    /// Total size of keys and values of the entries of `account_id` accounted
+    /// in its storage usage: the account record, the contract code, the access
+    /// keys and the contract data.
+    ///
+    /// Note that the storage usage of the account counts a fixed size for the
+    /// account record and a fixed overhead per access key and data record
+    /// instead of the raw size of their trie keys.
+    pub fn account_subtree_bytes(&self, account_id: &AccountId) -> Result<u64, StorageError> {
+        let mut total_bytes = 0;
+        for key in [
+            TrieKey::Account { account_id: account_id.clone() },
+            TrieKey::ContractCode { account_id: account_id.clone() },
+        ] {
+            let key = key.to_vec();
+            if let Some(value) = self.get(&key)? {
+                total_bytes += key.len() + value.len();
+            }
+        }
+        for prefix in [
+            trie_key_parsers::get_raw_prefix_for_access_keys(account_id),
+            trie_key_parsers::get_raw_prefix_for_contract_data(account_id, &[]),
+        ] {
+            let mut iter = self.iter()?;
+            iter.seek_prefix(&prefix)?;
+            for item in iter {
+                let (key, value) = item?;
+                total_bytes += key.len() + value.len();
+            }
+        }
+        Ok(total_bytes as u64)
+    }
 }
 
 impl TrieAccess for Trie {
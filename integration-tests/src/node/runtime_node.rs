@@ -2,8 +2,9 @@ use std::sync::{Arc, RwLock};
 
 use near_chain_configs::Genesis;
 use near_crypto::{InMemorySigner, KeyType, Signer};
-use near_parameters::RuntimeConfig;
+use near_parameters::{RuntimeConfig, RuntimeConfigStore};
 use near_primitives::types::AccountId;
+use near_primitives::version::ProtocolVersion;
 use testlib::runtime_utils::{add_test_contract, alice_account, bob_account, carol_account};
 
 use crate::node::Node;
@@ -26,6 +27,25 @@ impl RuntimeNode {
         Self::new_from_genesis(account_id, genesis)
     }
 
+    /// Like `new`, but with the genesis and the runtime config of the given
+    /// protocol version, as selected by `RuntimeConfigStore`.
+    pub fn new_with_protocol_version(
+        account_id: &AccountId,
+        protocol_version: ProtocolVersion,
+    ) -> Self {
+        let mut genesis = Genesis::test_with_protocol_version(
+            vec![alice_account(), bob_account(), carol_account()],
+            3,
+            protocol_version,
+        );
+        add_test_contract(&mut genesis, &alice_account());
+        add_test_contract(&mut genesis, &bob_account());
+        add_test_contract(&mut genesis, &carol_account());
+        let runtime_config_store = RuntimeConfigStore::new(None);
+        let runtime_config = runtime_config_store.get_config(protocol_version);
+        Self::new_from_genesis_and_config(account_id, genesis, RuntimeConfig::clone(runtime_config))
+    }
+
     pub fn new_from_genesis_and_config(
         account_id: &AccountId,
         genesis: Genesis,
@@ -114,4 +134,19 @@ mod tests {
         assert_eq!(alice2, alice1 - 1 - transfer_cost);
         assert_eq!(bob2, bob1 + 1);
     }
+
+    /// Protocol version 53 raised the execution cost of deploying contract bytes.
+    #[test]
+    pub fn test_fees_depend_on_protocol_version() {
+        let fee_helper = |protocol_version| {
+            let node = RuntimeNode::new_with_protocol_version(&alice_account(), protocol_version);
+            let runtime_config = node.client.as_ref().read().unwrap().runtime_config.clone();
+            FeeHelper::new(runtime_config, node.genesis().config.min_gas_price)
+        };
+        let (old_fee_helper, new_fee_helper) = (fee_helper(52), fee_helper(53));
+        assert_eq!(old_fee_helper.transfer_cost(), new_fee_helper.transfer_cost());
+        assert!(
+            old_fee_helper.deploy_contract_cost(1000) < new_fee_helper.deploy_contract_cost(1000)
+        );
+    }
 }
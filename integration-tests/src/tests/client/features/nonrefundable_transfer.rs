@@ -6,26 +6,50 @@
 //!
 //! NEP: https://github.com/near/NEPs/pull/491
 
+use borsh::BorshDeserialize;
+use near_async::time::Clock;
+use near_chain::near_chain_primitives::error::QueryError;
+use near_chain::{ChainStoreAccess, Provenance};
 use near_chain_configs::Genesis;
 use near_chain_configs::NEAR_BASE;
-use near_client::test_utils::TestEnv;
+use near_client::test_utils::{BalanceBreakdown, BurnBreakdown, TestEnv};
 use near_crypto::{InMemorySigner, KeyType, PublicKey};
+use near_network::client::ProcessTxResponse;
+use near_parameters::{ActionCosts, RuntimeConfigStore};
 use near_primitives::errors::{
     ActionError, ActionErrorKind, ActionsValidationError, InvalidTxError, TxExecutionError,
 };
+use near_primitives::receipt::Receipt;
+use near_primitives::test_utils::{
+    assert_all_failed_with, assert_all_success, assert_outcomes_equivalent_ignoring_gas,
+    create_implicit_test_signer, for_each_implicit_key_type,
+};
 use near_primitives::transaction::{
-    Action, AddKeyAction, CreateAccountAction, DeleteAccountAction, DeployContractAction,
-    NonrefundableStorageTransferAction, SignedTransaction, TransferAction,
+    Action, AddKeyAction, CreateAccountAction, DeleteAccountAction, DeleteAccountActionV2,
+    DeployContractAction, FunctionCallAction, NonrefundableStorageTransferAction,
+    SignedTransaction, TransferAction,
+};
+use near_primitives::trie_key::{col, trie_key_parsers, TrieKey};
+use near_primitives::types::{
+    AccountId, Balance, BlockId, BlockReference, Gas, NumBlocks, NumSeats, ShardId,
 };
-use near_primitives::types::{AccountId, Balance};
 use near_primitives::utils::{derive_eth_implicit_account_id, derive_near_implicit_account_id};
-use near_primitives::version::{ProtocolFeature, ProtocolVersion};
+use near_primitives::version::{ProtocolFeature, ProtocolVersion, PROTOCOL_VERSION};
 use near_primitives::views::{
-    ExecutionStatusView, FinalExecutionOutcomeView, QueryRequest, QueryResponseKind,
+    AccountView, ExecutionStatusView, FinalExecutionOutcomeView, FinalExecutionStatus,
+    QueryRequest, QueryResponseKind, ReceiptEnumView, RefundView,
 };
-use near_primitives_core::account::{AccessKey, AccessKeyPermission};
+use near_primitives_core::account::{AccessKey, AccessKeyPermission, Account, AccountVersion};
+use near_store::{get_delayed_receipt_indices, ShardUId, StoreCompiledContractCache};
+use near_vm_runner::logic::errors::CompilationError;
+use near_vm_runner::logic::{CompiledContract, CompiledContractCache};
+use near_vm_runner::{get_contract_cache_key, ContractCode};
 use nearcore::test_utils::TestEnvNightshadeSetupExt;
+use node_runtime::config::{deposit_for_storage_bytes, storage_bytes_covered};
 use testlib::fees_utils::FeeHelper;
+use testlib::transaction_builder::{TransactionBuilder, TransactionBuilderError};
+
+use std::collections::{BTreeMap, BTreeSet};
 
 use crate::node::RuntimeNode;
 
@@ -46,6 +70,39 @@ const TEST_CASES: [Transfers; 3] = [
     Transfers { regular_amount: 1, nonrefundable_amount: 1, nonrefundable_transfer_first: false },
 ];
 
+/// Builder starting with the actions of a sponsored account creation: create
+/// `account_id`, add a full access key derived from its name and fund it with
+/// `nonrefundable` only.
+fn sponsored_creation(account_id: &AccountId, nonrefundable: Balance) -> TransactionBuilder {
+    TransactionBuilder::new()
+        .create_account()
+        .add_key(PublicKey::from_seed(KeyType::ED25519, account_id.as_str()))
+        .nonrefundable_transfer(nonrefundable)
+}
+
+/// Actions of a sponsored account creation, see `sponsored_creation`.
+fn sponsored_creation_actions(account_id: &AccountId, nonrefundable: Balance) -> Vec<Action> {
+    sponsored_creation(account_id, nonrefundable).actions().to_vec()
+}
+
+/// Runs `f` for each of `TEST_CASES`, together with its index, in a fresh
+/// environment created by `setup`. `f` should mention the index in its
+/// assertion messages, so that a failure tells which case failed.
+fn for_each_test_case_with_env(
+    setup: impl Fn() -> TestEnv,
+    mut f: impl FnMut(&mut TestEnv, usize, Transfers),
+) {
+    for (index, transfers) in TEST_CASES.into_iter().enumerate() {
+        f(&mut setup(), index, transfers);
+    }
+}
+
+/// Same as `for_each_test_case_with_env`, with environments from `setup_env`.
+fn for_each_test_case(f: impl FnMut(&mut TestEnv, usize, Transfers)) {
+    for_each_test_case_with_env(setup_env, f)
+}
+
+#[derive(Debug)]
 struct TransferConfig {
     /// Describes transfers configuration we are interested in.
     transfers: Transfers,
@@ -74,18 +131,119 @@ fn signer() -> InMemorySigner {
 
 /// Creates a test environment using given protocol version (if some).
 fn setup_env_with_protocol_version(protocol_version: Option<ProtocolVersion>) -> TestEnv {
+    let accounts = vec![sender(), receiver()];
+    let genesis = match protocol_version {
+        Some(protocol_version) => {
+            Genesis::test_with_protocol_version(accounts, 1, protocol_version)
+        }
+        None => Genesis::test(accounts, 1),
+    };
+    TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build()
+}
+
+/// Asserts that a sponsored account creation has equivalent outcomes on chains
+/// started from genesis `a` and `b`, see `assert_outcomes_equivalent_ignoring_gas`.
+fn assert_genesis_behaviorally_equal(a: &Genesis, b: &Genesis) {
+    let outcomes: Vec<_> = [a, b]
+        .into_iter()
+        .map(|genesis| {
+            let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(genesis).build();
+            env.create_and_verify_sponsored_account(
+                &signer(),
+                "subaccount.test0".parse().unwrap(),
+                NEAR_BASE,
+                None,
+            )
+        })
+        .collect();
+    assert_outcomes_equivalent_ignoring_gas(&outcomes[0], &outcomes[1]);
+}
+
+/// Creates a test environment in which transactions expire after
+/// `validity_period` blocks.
+fn setup_env_with_transaction_validity_period(validity_period: NumBlocks) -> TestEnv {
     let mut genesis = Genesis::test(vec![sender(), receiver()], 1);
-    if let Some(protocol_version) = protocol_version {
-        genesis.config.protocol_version = protocol_version;
-    }
+    genesis.config.transaction_validity_period = validity_period;
     TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build()
 }
 
+/// Creates a test environment in which all blocks have the given gas price.
+fn setup_env_with_gas_price(gas_price: Balance) -> TestEnv {
+    let genesis = Genesis::test(vec![sender(), receiver()], 1);
+    TestEnv::builder(&genesis.config).gas_price(gas_price).nightshade_runtimes(&genesis).build()
+}
+
 /// Creates a test environment using default protocol version.
 fn setup_env() -> TestEnv {
     setup_env_with_protocol_version(None)
 }
 
+/// Creates a test environment with `num_validators` validators, each of them
+/// running a client. The default sender and receiver are among the validators.
+fn setup_env_with_validators(num_validators: usize) -> TestEnv {
+    assert!(num_validators >= 2, "sender and receiver must be validators");
+    let validators: Vec<AccountId> =
+        (0..num_validators).map(|i| format!("test{}", i).parse().unwrap()).collect();
+    let genesis = Genesis::test(validators.clone(), num_validators as NumSeats);
+    TestEnv::builder(&genesis.config)
+        .clients(validators.clone())
+        .validators(validators)
+        .nightshade_runtimes(&genesis)
+        .build()
+}
+
+/// Produces the next block by its designated block producer and processes it,
+/// together with its chunks, state witnesses and endorsements, on all clients.
+fn produce_block_on_all_clients(env: &mut TestEnv) {
+    let tip = env.clients[0].chain.head().unwrap();
+    let block_producer = env.get_block_producer_at_offset(&tip, 1);
+    let block = env.client(&block_producer).produce_block(tip.height + 1).unwrap().unwrap();
+    for i in 0..env.clients.len() {
+        env.clients[i].process_block_test(block.clone().into(), Provenance::NONE).unwrap();
+    }
+    env.process_partial_encoded_chunks();
+    for i in 0..env.clients.len() {
+        env.process_shards_manager_responses_and_finish_processing_blocks(i);
+    }
+    env.propagate_chunk_state_witnesses_and_endorsements(false);
+}
+
+/// Multi-validator counterpart of `execute_transaction_from_actions`.
+fn execute_transaction_from_actions_on_all_clients(
+    env: &mut TestEnv,
+    actions: Vec<Action>,
+    signer: &InMemorySigner,
+    receiver: AccountId,
+) -> FinalExecutionOutcomeView {
+    let tip = env.clients[0].chain.head().unwrap();
+    let nonce = get_nonce(env, signer);
+    let tx = SignedTransaction::from_actions(
+        nonce + 1,
+        signer.account_id.clone(),
+        receiver,
+        signer,
+        actions,
+        tip.last_block_hash,
+    );
+    let tx_hash = tx.get_hash();
+    for client in env.clients.iter_mut() {
+        let response = client.process_tx(tx.clone(), false, false);
+        assert!(!matches!(response, ProcessTxResponse::InvalidTx(_)), "{response:?}");
+    }
+    let max_iters = 100;
+    for _ in 0..max_iters {
+        produce_block_on_all_clients(env);
+        if let Ok(outcome) = env.clients[0].chain.get_final_transaction_result(&tx_hash) {
+            // Produce a couple more blocks so that all refunds are applied.
+            for _ in 0..2 {
+                produce_block_on_all_clients(env);
+            }
+            return outcome;
+        }
+    }
+    panic!("No transaction outcome found after {max_iters} blocks.")
+}
+
 fn fee_helper() -> FeeHelper {
     let node = RuntimeNode::new(&sender());
     crate::tests::standard_cases::fee_helper(&node)
@@ -123,12 +281,124 @@ fn execute_transaction_from_actions(
         actions,
         tip.last_block_hash,
     );
-    let tx_result = env.execute_tx(tx);
-    let height = env.clients[0].chain.head().unwrap().height;
-    for i in 0..2 {
-        env.produce_block(0, height + 1 + i);
+    env.execute_and_run_to_completion(tx)
+}
+
+/// VM-level details about a contract deployed by a transaction.
+#[derive(Debug)]
+struct DeployContractDetails {
+    code_size: usize,
+    /// Gas burnt by the receipt that deployed the contract, which includes the
+    /// compilation cost.
+    gas_burnt: Gas,
+    /// Result of the compilation the runtime did on deploy, as stored in the
+    /// compiled contract cache.
+    compilation_result: Result<(), CompilationError>,
+}
+
+/// Same as `execute_transaction_from_actions`, but if `verbose` is set also
+/// collects VM details for every contract deployed by the transaction.
+///
+/// The details are read from what the runtime actually did: the outcome of
+/// the receipt executing the deploy and the compiled contract cache it fills.
+fn execute_transaction_with_vm_details(
+    env: &mut TestEnv,
+    actions: Vec<Action>,
+    signer: &InMemorySigner,
+    receiver: AccountId,
+    verbose: bool,
+) -> (Result<FinalExecutionOutcomeView, InvalidTxError>, Vec<DeployContractDetails>) {
+    let codes: Vec<Vec<u8>> = actions
+        .iter()
+        .filter_map(|action| match action {
+            Action::DeployContract(DeployContractAction { code }) => Some(code.clone()),
+            _ => None,
+        })
+        .collect();
+    let result = execute_transaction_from_actions(env, actions, signer, receiver.clone());
+    let outcome = match &result {
+        Ok(outcome) if verbose && !codes.is_empty() => outcome,
+        _ => return (result, vec![]),
+    };
+
+    let gas_burnt = outcome
+        .receipts_outcome
+        .iter()
+        .find(|receipt_outcome| receipt_outcome.outcome.executor_id == receiver)
+        .expect("deploy receipt must have been executed")
+        .outcome
+        .gas_burnt;
+    let epoch_id = env.clients[0].chain.head().unwrap().epoch_id;
+    let config = env.get_runtime_config(0, epoch_id);
+    let cache = StoreCompiledContractCache::new(env.clients[0].runtime_adapter.store());
+    let vm_details = codes
+        .into_iter()
+        .map(|code| {
+            let code_size = code.len();
+            let key = get_contract_cache_key(&ContractCode::new(code, None), &config.wasm_config);
+            let compilation_result = match cache.get(&key).unwrap() {
+                Some(CompiledContract::Code(_)) => Ok(()),
+                Some(CompiledContract::CompileModuleError(err)) => Err(err),
+                None => panic!("deployed contract must have been precompiled"),
+            };
+            DeployContractDetails { code_size, gas_burnt, compilation_result }
+        })
+        .collect();
+    (result, vm_details)
+}
+
+/// Actions that `exec_transfers` submits for the given `config`, in order:
+///
+/// 1. `CreateAccount` and `AddKey`, if a named account is created.
+/// 2. The transfers, the non-refundable one first if so configured.
+/// 3. `DeployContract`, if configured.
+///
+/// Note that a non-refundable transfer creating an implicit account is only
+/// valid if it is the only action, which is not enforced here, so that tests
+/// can check that other actions are rejected.
+fn order_actions(receiver: &AccountId, config: &TransferConfig) -> Vec<Action> {
+    let mut builder = TransactionBuilder::new();
+    if config.account_creation && !config.implicit_account_creation {
+        builder = builder
+            .create_account()
+            .add_key(PublicKey::from_seed(KeyType::ED25519, receiver.as_str()));
     }
-    tx_result
+
+    let transfers = &config.transfers;
+    let add_nonrefundable_transfer = |builder: TransactionBuilder| {
+        if transfers.nonrefundable_amount > 0 {
+            builder.nonrefundable_transfer(transfers.nonrefundable_amount)
+        } else {
+            builder
+        }
+    };
+    let add_regular_transfer = |builder: TransactionBuilder| {
+        if transfers.regular_amount > 0 {
+            builder.transfer(transfers.regular_amount)
+        } else {
+            builder
+        }
+    };
+    builder = if transfers.nonrefundable_transfer_first {
+        add_regular_transfer(add_nonrefundable_transfer(builder))
+    } else {
+        add_nonrefundable_transfer(add_regular_transfer(builder))
+    };
+
+    if config.deploy_contract {
+        builder = builder.deploy(near_test_contracts::sized_contract(1500).to_vec());
+    }
+    builder.actions().to_vec()
+}
+
+/// Adding a key before creating the account is a bug in the test itself, so
+/// the builder refuses it right away.
+#[test]
+#[should_panic(expected = "create_account must be the first action")]
+fn transaction_builder_rejects_create_account_after_add_key() {
+    TransactionBuilder::new()
+        .add_key(PublicKey::from_seed(KeyType::ED25519, "subaccount.test0"))
+        .create_account();
 }
 
 /// Submits a transfer (regular, non-refundable, or both).
@@ -143,42 +413,14 @@ fn exec_transfers(
     config: TransferConfig,
 ) -> Result<FinalExecutionOutcomeView, InvalidTxError> {
     let sender_pre_balance = env.query_balance(sender());
-    let (receiver_before_amount, receiver_before_nonrefundable) = if config.account_creation {
-        (0, 0)
+    let receiver_before = if config.account_creation {
+        BalanceBreakdown::default()
     } else {
-        let receiver_before = env.query_account(receiver.clone());
-        (receiver_before.amount, receiver_before.nonrefundable)
+        env.query_balance_breakdown(receiver.clone())
     };
 
-    let mut actions = vec![];
-
-    if config.account_creation && !config.implicit_account_creation {
-        actions.push(Action::CreateAccount(CreateAccountAction {}));
-        actions.push(Action::AddKey(Box::new(AddKeyAction {
-            public_key: PublicKey::from_seed(KeyType::ED25519, receiver.as_str()),
-            access_key: AccessKey { nonce: 0, permission: AccessKeyPermission::FullAccess },
-        })));
-    }
-
-    if config.transfers.nonrefundable_transfer_first && config.transfers.nonrefundable_amount > 0 {
-        actions.push(Action::NonrefundableStorageTransfer(NonrefundableStorageTransferAction {
-            deposit: config.transfers.nonrefundable_amount,
-        }));
-    }
-    if config.transfers.regular_amount > 0 {
-        actions.push(Action::Transfer(TransferAction { deposit: config.transfers.regular_amount }));
-    }
-    if !config.transfers.nonrefundable_transfer_first && config.transfers.nonrefundable_amount > 0 {
-        actions.push(Action::NonrefundableStorageTransfer(NonrefundableStorageTransferAction {
-            deposit: config.transfers.nonrefundable_amount,
-        }));
-    }
-
-    if config.deploy_contract {
-        let contract = near_test_contracts::sized_contract(1500 as usize);
-        actions.push(Action::DeployContract(DeployContractAction { code: contract.to_vec() }))
-    }
-
+    let nonce_before = get_nonce(env, &signer);
+    let actions = order_actions(&receiver, &config);
     let tx_result = execute_transaction_from_actions(env, actions, &signer, receiver.clone());
 
     let outcome = match &tx_result {
@@ -192,6 +434,10 @@ fn exec_transfers(
         return tx_result;
     }
 
+    if !config.account_creation && signer.account_id == sender() {
+        env.assert_nonce_advanced(&signer, nonce_before);
+    }
+
     let gas_cost = outcome.tokens_burnt();
     assert_eq!(
         sender_pre_balance
@@ -201,12 +447,16 @@ fn exec_transfers(
         env.query_balance(sender())
     );
 
-    let receiver_expected_amount_after = receiver_before_amount + config.transfers.regular_amount;
-    let receiver_expected_non_refundable_after =
-        receiver_before_nonrefundable + config.transfers.nonrefundable_amount;
-    let receiver_after = env.query_account(receiver);
-    assert_eq!(receiver_after.amount, receiver_expected_amount_after);
-    assert_eq!(receiver_after.nonrefundable, receiver_expected_non_refundable_after);
+    let receiver_after = env.query_balance_breakdown(receiver);
+    assert_eq!(
+        receiver_after.refundable,
+        receiver_before.refundable + config.transfers.regular_amount
+    );
+    assert_eq!(
+        receiver_after.nonrefundable,
+        receiver_before.nonrefundable + config.transfers.nonrefundable_amount
+    );
+    assert_eq!(receiver_after.locked, receiver_before.locked);
 
     tx_result
 }
@@ -220,10 +470,391 @@ fn delete_account(
     execute_transaction_from_actions(env, actions, &signer, signer.account_id.clone())
 }
 
-/// Can delete account with non-refundable storage.
+/// The balance breakdown of a freshly created sponsored account consists of
+/// the transferred regular and non-refundable amounts.
 #[test]
-fn deleting_account_with_non_refundable_storage() {
+fn sponsored_account_balance_breakdown() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let regular_amount = 10u128.pow(20);
+    let nonrefundable_amount = NEAR_BASE;
+    exec_transfers(
+        &mut env,
+        signer(),
+        new_account_id.clone(),
+        TransferConfig {
+            transfers: Transfers {
+                regular_amount,
+                nonrefundable_amount,
+                nonrefundable_transfer_first: true,
+            },
+            account_creation: true,
+            implicit_account_creation: false,
+            deploy_contract: false,
+        },
+    )
+    .unwrap()
+    .assert_success();
+
+    assert_eq!(
+        env.query_balance_breakdown(new_account_id),
+        BalanceBreakdown {
+            refundable: regular_amount,
+            nonrefundable: nonrefundable_amount,
+            locked: 0,
+            total: regular_amount + nonrefundable_amount,
+        }
+    );
+}
+
+/// A sponsored account can be part of the genesis state.
+#[test]
+fn sponsored_account_in_genesis() {
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let genesis = Genesis::test_with_sponsored_account(
+        vec![sender(), receiver()],
+        1,
+        new_account_id.clone(),
+        NEAR_BASE,
+    );
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    let account = env.query_account(new_account_id);
+    assert_eq!(account.amount, 0);
+    assert_eq!(account.nonrefundable, NEAR_BASE);
+}
+
+/// Genesis accounts can start with both a regular and a non-refundable balance.
+#[test]
+fn genesis_accounts_with_non_refundable_balance() {
+    let genesis = Genesis::test_with_balances(vec![
+        (sender(), NEAR_BASE, 0),
+        (receiver(), 2 * NEAR_BASE, NEAR_BASE),
+    ]);
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    let sender_account = env.query_account(sender());
+    assert_eq!(sender_account.amount, NEAR_BASE);
+    assert_eq!(sender_account.nonrefundable, 0);
+    let receiver_account = env.query_account(receiver());
+    assert_eq!(receiver_account.amount, 2 * NEAR_BASE);
+    assert_eq!(receiver_account.nonrefundable, NEAR_BASE);
+}
+
+/// A named account created with a single full-access key has exactly that key.
+#[test]
+fn sponsored_named_account_has_single_full_access_key() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let public_key = PublicKey::from_seed(KeyType::ED25519, new_account_id.as_str());
+    let actions = TransactionBuilder::new()
+        .create_account()
+        .add_key(public_key.clone())
+        .nonrefundable_transfer(NEAR_BASE)
+        .actions()
+        .to_vec();
+    execute_transaction_from_actions(&mut env, actions, &signer(), new_account_id.clone())
+        .unwrap()
+        .assert_success();
+
+    let access_key_list = env.query_access_key_list(new_account_id);
+    assert_eq!(access_key_list.keys.len(), 1);
+    let access_key_info = &access_key_list.keys[0];
+    assert_eq!(access_key_info.public_key, public_key);
+    let access_key = AccessKey::from(access_key_info.access_key.clone());
+    assert_eq!(access_key.permission, AccessKeyPermission::FullAccess);
+}
+
+/// The balance history of a sponsored account starts with the non-refundable
+/// balance at the height of its creation and follows later transfers.
+#[test]
+fn sponsored_account_balance_history() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let from_height = env.clients[0].chain.head().unwrap().height;
+    let actions = sponsored_creation_actions(&new_account_id, NEAR_BASE);
+    let tx_result =
+        execute_transaction_from_actions(&mut env, actions, &signer(), new_account_id.clone())
+            .unwrap();
+    tx_result.assert_success();
+    let creation_block_hash = tx_result.receipts_outcome[0].block_hash;
+    let creation_height =
+        env.clients[0].chain.get_block_header(&creation_block_hash).unwrap().height();
+
+    let regular_amount = 10u128.pow(20);
+    let actions = vec![Action::Transfer(TransferAction { deposit: regular_amount })];
+    execute_transaction_from_actions(&mut env, actions, &signer(), new_account_id.clone())
+        .unwrap()
+        .assert_success();
+
+    let chain = &env.clients[0].chain;
+    let to_height = chain.head().unwrap().height;
+    let history =
+        chain.account_balance_history(&new_account_id, from_height, to_height, 1).unwrap();
+    assert_eq!(history.first(), Some(&(creation_height, 0, NEAR_BASE)));
+    assert_eq!(history.last(), Some(&(to_height, regular_amount, NEAR_BASE)));
+    assert!(matches!(
+        chain.account_balance_history(&new_account_id, from_height, to_height, 0),
+        Err(near_chain::Error::Other(_))
+    ));
+}
+
+/// Makes the test contract deployed on `account_id` write a record that uses
+/// exactly `num_bytes` of storage, with the storage paid by the account.
+fn grow_state(
+    env: &mut TestEnv,
+    account_id: AccountId,
+    num_bytes: u64,
+) -> FinalExecutionOutcomeView {
+    let epoch_id = env.clients[0].chain.head().unwrap().epoch_id;
+    let storage_config = env.get_runtime_config(0, epoch_id).fees.storage_usage_config;
+    let value = 0u64.to_le_bytes();
+    let key_len = num_bytes - storage_config.num_extra_bytes_record - value.len() as u64;
+    let args = [vec![b'k'; key_len as usize], value.to_vec()].concat();
+    let actions = vec![Action::FunctionCall(Box::new(FunctionCallAction {
+        method_name: "write_key_value".to_string(),
+        args,
+        gas: 100 * 10u64.pow(12),
+        deposit: 0,
+    }))];
+    execute_transaction_from_actions(env, actions, &signer(), account_id).unwrap()
+}
+
+/// The non-refundable balance of a sponsored account is a hard cap for its
+/// storage: the state can grow up to the covered size, but not beyond.
+#[test]
+fn sponsored_account_cannot_grow_state_beyond_coverage() {
+    let mut env = setup_env();
+    let fee_helper = fee_helper();
+    let create_account = |env: &mut TestEnv, account_id: &AccountId, nonrefundable_amount| {
+        let actions = sponsored_creation(account_id, nonrefundable_amount)
+            .deploy(near_test_contracts::rs_contract().to_vec())
+            .actions()
+            .to_vec();
+        execute_transaction_from_actions(env, actions, &signer(), account_id.clone())
+            .unwrap()
+            .assert_success();
+        env.query_account(account_id.clone())
+    };
+
+    // Accounts with a single key of the same type and the same contract use
+    // the same number of bytes, so a probe tells the storage usage after the
+    // creation.
+    let probe_id: AccountId = "probe.test0".parse().unwrap();
+    let used_bytes = create_account(&mut env, &probe_id, 10 * NEAR_BASE).storage_usage;
+    let headroom = 1000;
+    let sponsored_id: AccountId = "sponsored.test0".parse().unwrap();
+    let nonrefundable_amount =
+        (used_bytes + headroom) as Balance * fee_helper.rt_cfg.storage_amount_per_byte();
+    let account = create_account(&mut env, &sponsored_id, nonrefundable_amount);
+    assert_eq!(fee_helper.storage_coverage(&account).headroom(), headroom as i128);
+
+    grow_state(&mut env, sponsored_id.clone(), headroom).assert_success();
+    let account = env.query_account(sponsored_id.clone());
+    assert_eq!(fee_helper.storage_coverage(&account).headroom(), 0);
+
+    let epoch_id = env.clients[0].chain.head().unwrap().epoch_id;
+    let min_record_size =
+        env.get_runtime_config(0, epoch_id).fees.storage_usage_config.num_extra_bytes_record + 9;
+    let tx_result = grow_state(&mut env, sponsored_id.clone(), min_record_size);
+    let status = &tx_result.receipts_outcome[0].outcome.status;
+    assert!(matches!(
+        status,
+        ExecutionStatusView::Failure(TxExecutionError::ActionError(
+            ActionError { kind: ActionErrorKind::LackBalanceForState { account_id, .. }, .. }
+        )) if *account_id == sponsored_id,
+    ));
+    env.assert_account_unchanged(sponsored_id, &account);
+}
+
+/// Only non-refundable transfers change the non-refundable balance: receiving
+/// regular transfers and executing function calls on a sponsored account,
+/// even ones paying for storage, keep it unchanged.
+#[test]
+fn non_refundable_balance_unchanged_by_transfers_and_calls() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let nonrefundable_amount = 10 * NEAR_BASE;
+    env.create_and_verify_sponsored_account(
+        &signer(),
+        new_account_id.clone(),
+        nonrefundable_amount,
+        Some(near_test_contracts::rs_contract().to_vec()),
+    );
+    let assert_nonrefundable_unchanged = |env: &mut TestEnv| {
+        assert_eq!(env.query_account(new_account_id.clone()).nonrefundable, nonrefundable_amount);
+    };
+
+    exec_transfers(
+        &mut env,
+        signer(),
+        new_account_id.clone(),
+        TransferConfig {
+            transfers: Transfers {
+                regular_amount: NEAR_BASE,
+                nonrefundable_amount: 0,
+                nonrefundable_transfer_first: false,
+            },
+            account_creation: false,
+            implicit_account_creation: false,
+            deploy_contract: false,
+        },
+    )
+    .unwrap()
+    .assert_success();
+    assert_nonrefundable_unchanged(&mut env);
+
+    grow_state(&mut env, new_account_id.clone(), 100).assert_success();
+    assert_nonrefundable_unchanged(&mut env);
+}
+
+/// Full lifecycle of a sponsored account: creation with non-refundable storage
+/// and some refundable balance, deployment of a contract, a call mutating its
+/// state and finally deletion.
+///
+/// At every stage the total supply only drops by the burnt gas, minus the gas
+/// reward paid to the contract, and on deletion additionally by the discarded
+/// non-refundable balance. The beneficiary only receives the refundable rest.
+#[test]
+fn sponsored_account_lifecycle() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let new_account = InMemorySigner::from_seed(
+        new_account_id.clone(),
+        KeyType::ED25519,
+        new_account_id.as_str(),
+    );
+    let regular_amount = NEAR_BASE / 10;
+    let nonrefundable_amount = 10 * NEAR_BASE;
+
+    // Create and fund the account, deploying a contract.
+    let actions = TransactionBuilder::new()
+        .create_account()
+        .add_key(new_account.public_key.clone())
+        .transfer(regular_amount)
+        .nonrefundable_transfer(nonrefundable_amount)
+        .deploy(near_test_contracts::rs_contract().to_vec())
+        .actions()
+        .to_vec();
+    let tx_result =
+        execute_transaction_from_actions(&mut env, actions, &signer(), new_account_id.clone())
+            .unwrap();
+    tx_result.assert_success();
+    env.assert_supply_delta(tx_result.tokens_burnt(), 0);
+    let account_before_call = env.query_account(new_account_id.clone());
+    assert_eq!(account_before_call.amount, regular_amount);
+    assert_eq!(account_before_call.nonrefundable, nonrefundable_amount);
+    assert!(fee_helper().storage_coverage(&account_before_call).is_funded());
+
+    // Call the contract to write to its state.
+    let tx_result = grow_state(&mut env, new_account_id.clone(), 100);
+    tx_result.assert_success();
+    let account_before_deletion = env.query_account(new_account_id.clone());
+    assert_eq!(account_before_deletion.storage_usage, account_before_call.storage_usage + 100);
+    assert_eq!(account_before_deletion.nonrefundable, nonrefundable_amount);
+    let gas_reward = account_before_deletion.amount - account_before_call.amount;
+    env.assert_supply_delta(tx_result.tokens_burnt() - gas_reward, 0);
+
+    // Delete the account.
+    let beneficiary_before = env.query_account(receiver());
+    let tx_result = delete_account(&mut env, &new_account, receiver()).unwrap();
+    tx_result.assert_success();
+    assert!(!account_exists(&mut env, new_account_id));
+    env.assert_supply_delta(tx_result.tokens_burnt(), nonrefundable_amount);
+    let beneficiary_after = env.query_account(receiver());
+    assert_eq!(
+        beneficiary_after.amount,
+        fee_helper().expected_beneficiary_balance(
+            beneficiary_before.amount,
+            account_before_deletion.amount
+        )
+    );
+    assert_eq!(beneficiary_after.nonrefundable, beneficiary_before.nonrefundable);
+}
+
+/// Deploying a contract when creating a sponsored account grows its storage
+/// usage, which the non-refundable and regular balance still cover.
+#[test]
+fn sponsored_account_storage_usage_covered_with_contract() {
+    let mut env = setup_env();
+    let fee_helper = fee_helper();
+    let transfers = Transfers {
+        regular_amount: NEAR_BASE,
+        nonrefundable_amount: NEAR_BASE,
+        nonrefundable_transfer_first: true,
+    };
+    let mut storage_usage = vec![];
+    for deploy_contract in [false, true] {
+        let new_account_id: AccountId =
+            format!("subaccount{}.test0", storage_usage.len()).parse().unwrap();
+        exec_transfers(
+            &mut env,
+            signer(),
+            new_account_id.clone(),
+            TransferConfig {
+                transfers: transfers.clone(),
+                account_creation: true,
+                implicit_account_creation: false,
+                deploy_contract,
+            },
+        )
+        .unwrap()
+        .assert_success();
+        let account = env.query_account(new_account_id.clone());
+        assert_eq!(env.query_storage_usage(new_account_id), account.storage_usage);
+        assert!(
+            account.storage_usage as Balance * fee_helper.rt_cfg.storage_amount_per_byte()
+                <= account.nonrefundable + account.amount
+        );
+        storage_usage.push(account.storage_usage);
+    }
+    assert!(
+        storage_usage[1] >= storage_usage[0] + 1500,
+        "storage usage {storage_usage:?} did not grow by the contract size"
+    );
+}
+
+/// A sponsored account created in blocks which are not final yet is gone
+/// after a reorg to a higher competing fork that does not include the
+/// transaction creating it.
+#[test]
+fn sponsored_account_creation_reverted_by_reorg() {
     let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let fork_point = env.clients[0].chain.head().unwrap().last_block_hash;
+
+    let actions = order_actions(
+        &new_account_id,
+        &TransferConfig {
+            transfers: TEST_CASES[0].clone(),
+            account_creation: true,
+            implicit_account_creation: false,
+            deploy_contract: false,
+        },
+    );
+    let signer = signer();
+    let tx = SignedTransaction::from_actions(
+        get_nonce(&mut env, &signer) + 1,
+        signer.account_id.clone(),
+        new_account_id.clone(),
+        &signer,
+        actions,
+        fork_point,
+    );
+    env.execute_tx_without_finalizing(tx).unwrap().assert_success();
+    assert_eq!(
+        env.query_account(new_account_id.clone()).nonrefundable,
+        TEST_CASES[0].nonrefundable_amount
+    );
+
+    let fork_tip = env.produce_blocks_on_fork(fork_point, 3);
+    assert_eq!(env.clients[0].chain.head().unwrap().last_block_hash, fork_tip);
+    assert!(!account_exists(&mut env, new_account_id));
+}
+
+/// Creates an account with non-refundable storage, deletes it and checks the
+/// balances after the deletion. Returns the outcome of the deletion.
+fn check_deleting_account_with_non_refundable_storage(
+    env: &mut TestEnv,
+) -> FinalExecutionOutcomeView {
     let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
     let new_account = InMemorySigner::from_seed(
         new_account_id.clone(),
@@ -236,7 +867,7 @@ fn deleting_account_with_non_refundable_storage() {
     // Send some NEAR (refundable) so that the new account is able to pay the gas for its deletion in the next transaction.
     // Deploy a contract that does not fit within Zero-balance account limit.
     let create_account_tx_result = exec_transfers(
-        &mut env,
+        env,
         signer(),
         new_account_id.clone(),
         TransferConfig {
@@ -250,43 +881,65 @@ fn deleting_account_with_non_refundable_storage() {
             deploy_contract: true,
         },
     );
-    create_account_tx_result.unwrap().assert_success();
+    create_account_tx_result.unwrap().assert_all_actions_applied();
 
     // Delete the new account (that has 1 NEAR of non-refundable balance).
     let beneficiary_id = receiver();
     let beneficiary_before = env.query_account(beneficiary_id.clone());
-    let delete_account_tx_result = delete_account(&mut env, &new_account, beneficiary_id.clone());
-    delete_account_tx_result.unwrap().assert_success();
-    assert!(!account_exists(&mut env, new_account_id));
+    let delete_account_tx_result =
+        delete_account(env, &new_account, beneficiary_id.clone()).unwrap();
+    delete_account_tx_result.assert_success();
+    assert!(!account_exists(env, new_account_id.clone()));
+    env.assert_code_removed(&new_account_id);
+    // The non-refundable balance is burnt together with the gas.
+    assert_eq!(
+        env.burn_breakdown(&delete_account_tx_result),
+        BurnBreakdown {
+            gas_burnt: delete_account_tx_result.tokens_burnt(),
+            discarded_nonrefundable: nonrefundable_amount,
+        }
+    );
+    env.assert_supply_delta(delete_account_tx_result.tokens_burnt(), nonrefundable_amount);
 
     // Check that the beneficiary account received the remaining balance from the deleted account,
     // but none of the non-refundable balance.
     let beneficiary_after = env.query_account(beneficiary_id);
+    let fee_helper = fee_helper();
+    let delete_cost = fee_helper.delete_account_cost_breakdown();
+    assert_eq!(delete_cost.total(), fee_helper.prepaid_delete_account_cost());
     assert_eq!(
         beneficiary_after.amount,
-        beneficiary_before.amount + regular_amount - fee_helper().prepaid_delete_account_cost()
+        fee_helper.expected_beneficiary_balance(beneficiary_before.amount, regular_amount)
     );
     assert_eq!(beneficiary_after.nonrefundable, beneficiary_before.nonrefundable);
+    delete_account_tx_result
 }
 
-/// Non-refundable balance cannot be transferred.
+/// Can delete account with non-refundable storage.
 #[test]
-fn non_refundable_balance_cannot_be_transferred() {
-    let mut env = setup_env();
+fn deleting_account_with_non_refundable_storage() {
+    check_deleting_account_with_non_refundable_storage(&mut setup_env());
+}
+
+/// Creates a sponsored account with some refundable balance to pay for gas and
+/// submits a `DeleteAccountV2` action for it with the given flag.
+fn delete_sponsored_account(
+    env: &mut TestEnv,
+    allow_nonrefundable_burn: bool,
+) -> FinalExecutionOutcomeView {
     let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
     let new_account = InMemorySigner::from_seed(
         new_account_id.clone(),
         KeyType::ED25519,
         new_account_id.as_str(),
     );
-    // The `new_account` is created with 1 NEAR non-refundable balance.
-    let create_account_tx_result = exec_transfers(
-        &mut env,
+    exec_transfers(
+        env,
         signer(),
         new_account_id.clone(),
         TransferConfig {
             transfers: Transfers {
-                regular_amount: 0,
+                regular_amount: 10u128.pow(20),
                 nonrefundable_amount: NEAR_BASE,
                 nonrefundable_transfer_first: true,
             },
@@ -294,40 +947,1908 @@ fn non_refundable_balance_cannot_be_transferred() {
             implicit_account_creation: false,
             deploy_contract: false,
         },
+    )
+    .unwrap()
+    .assert_success();
+    let actions = vec![Action::DeleteAccountV2(Box::new(DeleteAccountActionV2 {
+        beneficiary_id: receiver(),
+        allow_nonrefundable_burn,
+    }))];
+    execute_transaction_from_actions(env, actions, &new_account, new_account_id).unwrap()
+}
+
+/// Deleting a sponsored account burns its non-refundable balance if the
+/// `DeleteAccountV2` action explicitly allows it.
+#[test]
+fn deleting_account_with_allowed_non_refundable_burn() {
+    let mut env = setup_env();
+    delete_sponsored_account(&mut env, true).assert_success();
+    assert!(!account_exists(&mut env, "subaccount.test0".parse().unwrap()));
+}
+
+/// Deleting a sponsored account fails if the `DeleteAccountV2` action does not
+/// allow burning its non-refundable balance, and the account is kept.
+#[test]
+fn reject_deleting_account_without_allowed_non_refundable_burn() {
+    let mut env = setup_env();
+    let account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let outcome = delete_sponsored_account(&mut env, false);
+    assert_eq!(
+        outcome.status,
+        FinalExecutionStatus::Failure(TxExecutionError::ActionError(ActionError {
+            index: Some(0),
+            kind: ActionErrorKind::CannotDeleteAccountWithNonrefundableBalance {
+                account_id: account_id.clone(),
+                nonrefundable: NEAR_BASE,
+            },
+        }))
+    );
+    assert_eq!(env.query_account(account_id).nonrefundable, NEAR_BASE);
+}
+
+/// Asserts that the total supply of the head block is held by the state it
+/// accounts for, the state before the chunks of the head block: the
+/// refundable, non-refundable and locked balances of all accounts, plus the
+/// receipts not applied yet and the rewards minted at the head block.
+///
+/// Assumes that there are no receipts waiting for input data and no gas price
+/// deficits.
+#[track_caller]
+fn assert_supply_consistent(env: &TestEnv) {
+    let client = &env.clients[0];
+    let head = client.chain.head().unwrap();
+    let head_block = client.chain.get_block(&head.last_block_hash).unwrap();
+    let config = env.get_runtime_config(0, head.epoch_id);
+
+    let mut accounts = vec![];
+    let mut receipts_balance: Balance = 0;
+    for shard_id in client.epoch_manager.shard_ids(&head.epoch_id).unwrap() {
+        let trie = client
+            .runtime_adapter
+            .get_view_trie_for_shard(
+                shard_id,
+                &head.prev_block_hash,
+                head_block.chunks()[shard_id as usize].prev_state_root(),
+            )
+            .unwrap();
+
+        let mut iter = trie.iter().unwrap();
+        iter.seek_prefix([col::ACCOUNT]).unwrap();
+        for item in iter {
+            let (key, value) = item.unwrap();
+            let account_id = trie_key_parsers::parse_account_id_from_account_key(&key).unwrap();
+            let account = Account::try_from_slice(&value).unwrap();
+            accounts.push((account_id, account));
+        }
+
+        let indices = get_delayed_receipt_indices(&trie).unwrap();
+        let delayed_receipts =
+            (indices.first_index..indices.next_available_index).filter_map(|index| {
+                near_store::get::<Receipt>(&trie, &TrieKey::DelayedReceipt { index }).unwrap()
+            });
+        let incoming_receipts = client
+            .chain
+            .chain_store()
+            .get_outgoing_receipts(&head.prev_block_hash, shard_id)
+            .unwrap();
+        receipts_balance += incoming_receipts
+            .iter()
+            .cloned()
+            .chain(delayed_receipts)
+            .map(|receipt| node_runtime::receipt_cost(&config, &receipt).unwrap())
+            .sum::<Balance>();
+    }
+    let accounts_balance: Balance = accounts
+        .iter()
+        .map(|(_, account)| account.amount() + account.locked() + account.nonrefundable())
+        .sum();
+
+    let minted = if client.epoch_manager.is_next_block_epoch_start(&head.prev_block_hash).unwrap() {
+        client.epoch_manager.get_epoch_minted_amount(head_block.header().next_epoch_id()).unwrap()
+    } else {
+        0
+    };
+
+    let total_supply = head_block.header().total_supply();
+    let held = accounts_balance + receipts_balance + minted;
+    if held != total_supply {
+        let balances: Vec<String> = accounts
+            .iter()
+            .map(|(account_id, account)| {
+                format!(
+                    "{account_id}: amount {}, locked {}, nonrefundable {}",
+                    account.amount(),
+                    account.locked(),
+                    account.nonrefundable()
+                )
+            })
+            .collect();
+        panic!(
+            "total supply {total_supply} at height {} does not match the {held} held by \
+             the accounts ({accounts_balance}), receipts ({receipts_balance}) and minted \
+             rewards ({minted}), accounts:\n{}",
+            head.height,
+            balances.join("\n")
+        );
+    }
+}
+
+/// Produces `num_blocks` blocks, checking after each of them that the total
+/// supply is held by the accounts and the receipts in flight.
+fn produce_blocks_with_consistent_supply(env: &mut TestEnv, num_blocks: u64) {
+    let tip = env.clients[0].chain.head().unwrap();
+    for i in 1..=num_blocks {
+        env.produce_block(0, tip.height + i);
+        assert_supply_consistent(env);
+    }
+}
+
+/// Creating an account with a contract and non-refundable storage yields the
+/// same outcome when replayed on an independent env with the same genesis.
+#[test]
+fn sponsored_account_with_contract_deterministic_replay() {
+    let mut env = setup_env();
+    let mut replay_env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let config = TransferConfig {
+        transfers: Transfers {
+            regular_amount: 10u128.pow(20),
+            nonrefundable_amount: NEAR_BASE,
+            nonrefundable_transfer_first: true,
+        },
+        account_creation: true,
+        implicit_account_creation: false,
+        deploy_contract: true,
+    };
+    let actions = order_actions(&new_account_id, &config);
+    let tx = env.tx_from_actions(actions, &signer(), new_account_id);
+    env.assert_deterministic_replay(&mut replay_env, tx);
+}
+
+/// The total supply stays consistent with the balances of all accounts at
+/// each block while an account with non-refundable storage is created and
+/// deleted, so the non-refundable balance neither leaks nor is double-counted.
+#[test]
+fn supply_consistent_while_deleting_account_with_non_refundable_storage() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let new_account = InMemorySigner::from_seed(
+        new_account_id.clone(),
+        KeyType::ED25519,
+        new_account_id.as_str(),
+    );
+    produce_blocks_with_consistent_supply(&mut env, 1);
+
+    let config = TransferConfig {
+        transfers: Transfers {
+            regular_amount: 10u128.pow(20),
+            nonrefundable_amount: NEAR_BASE,
+            nonrefundable_transfer_first: true,
+        },
+        account_creation: true,
+        implicit_account_creation: false,
+        deploy_contract: true,
+    };
+    let actions = order_actions(&new_account_id, &config);
+    let tx = env.tx_from_actions(actions, &signer(), new_account_id.clone());
+    assert_eq!(env.clients[0].process_tx(tx, false, false), ProcessTxResponse::ValidTx);
+    produce_blocks_with_consistent_supply(&mut env, 5);
+    assert_eq!(env.query_account(new_account_id.clone()).nonrefundable, NEAR_BASE);
+
+    let actions = vec![Action::DeleteAccount(DeleteAccountAction { beneficiary_id: receiver() })];
+    let tx = env.tx_from_actions(actions, &new_account, new_account_id.clone());
+    assert_eq!(env.clients[0].process_tx(tx, false, false), ProcessTxResponse::ValidTx);
+    produce_blocks_with_consistent_supply(&mut env, 5);
+    assert!(!account_exists(&mut env, new_account_id));
+}
+
+/// Deleting an account with non-refundable storage behaves the same at the
+/// protocol version introducing non-refundable transfers and at the latest one.
+#[test]
+fn deleting_account_with_non_refundable_storage_across_versions() {
+    let outcomes: Vec<_> =
+        [ProtocolFeature::NonRefundableBalance.protocol_version(), PROTOCOL_VERSION]
+            .into_iter()
+            .map(|protocol_version| {
+                let mut env = setup_env_with_protocol_version(Some(protocol_version));
+                check_deleting_account_with_non_refundable_storage(&mut env)
+            })
+            .collect();
+    assert_outcomes_equivalent_ignoring_gas(&outcomes[0], &outcomes[1]);
+}
+
+/// `Genesis::test_with_protocol_version` behaves the same as setting the
+/// protocol version of `Genesis::test` manually.
+#[test]
+fn genesis_with_protocol_version_matches_manual_config() {
+    for protocol_version in
+        [ProtocolFeature::NonRefundableBalance.protocol_version(), PROTOCOL_VERSION]
+    {
+        let mut manual = Genesis::test(vec![sender(), receiver()], 1);
+        manual.config.protocol_version = protocol_version;
+        let helper =
+            Genesis::test_with_protocol_version(vec![sender(), receiver()], 1, protocol_version);
+        assert_eq!(helper.config.protocol_version, protocol_version);
+        assert_genesis_behaviorally_equal(&manual, &helper);
+    }
+}
+
+/// The trie footprint of a sponsored account with a contract corresponds to its
+/// storage usage, once the fixed sizes counted by the storage usage are
+/// replaced by the raw sizes of the trie entries.
+#[test]
+fn sponsored_account_subtree_bytes() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    env.create_and_verify_sponsored_account(
+        &signer(),
+        new_account_id.clone(),
+        10 * NEAR_BASE,
+        Some(near_test_contracts::rs_contract().to_vec()),
+    );
+    let client = &env.clients[0];
+    let head = client.chain.head().unwrap();
+    let chunk_extra =
+        client.chain.get_chunk_extra(&head.last_block_hash, &ShardUId::single_shard()).unwrap();
+    let trie = client
+        .runtime_adapter
+        .get_view_trie_for_shard(0, &head.last_block_hash, *chunk_extra.state_root())
+        .unwrap();
+    let subtree_bytes = trie.account_subtree_bytes(&new_account_id).unwrap();
+
+    let account = env.query_account_raw(new_account_id.clone());
+    let storage_config = env.get_runtime_config(0, head.epoch_id).fees.storage_usage_config;
+    // Trie keys of the account record and the contract code are the column
+    // byte followed by the account id, the access key additionally has the
+    // column byte as separator before the public key.
+    let id_len = new_account_id.len() as u64;
+    let account_record_bytes = 1 + id_len + borsh::object_length(&account).unwrap() as u64;
+    let code_key_bytes = 1 + id_len;
+    let access_key_prefix_bytes = 2 + id_len;
+    assert_eq!(
+        subtree_bytes,
+        account.storage_usage()
+            - storage_config.num_bytes_account
+            - storage_config.num_extra_bytes_record
+            + account_record_bytes
+            + code_key_bytes
+            + access_key_prefix_bytes
+    );
+}
+
+/// Keys of all state entries of the single shard, as of the head block.
+fn state_keys(env: &TestEnv) -> BTreeSet<Vec<u8>> {
+    let client = &env.clients[0];
+    let head = client.chain.head().unwrap();
+    let chunk_extra =
+        client.chain.get_chunk_extra(&head.last_block_hash, &ShardUId::single_shard()).unwrap();
+    let trie = client
+        .runtime_adapter
+        .get_view_trie_for_shard(0, &head.last_block_hash, *chunk_extra.state_root())
+        .unwrap();
+    trie.iter().unwrap().map(|item| item.unwrap().0).collect()
+}
+
+/// Creates a sponsored account and deletes it again `n` times, asserting
+/// after each cycle that the state holds exactly the same keys as before the
+/// first one and that the sponsor's storage usage is unchanged.
+fn create_delete_cycles(env: &mut TestEnv, n: usize) {
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let new_account = InMemorySigner::from_seed(
+        new_account_id.clone(),
+        KeyType::ED25519,
+        new_account_id.as_str(),
+    );
+    let baseline_keys = state_keys(env);
+    let baseline_storage_usage = env.query_account(sender()).storage_usage;
+    for cycle in 0..n {
+        let actions = TransactionBuilder::new()
+            .create_account()
+            .add_key(new_account.public_key.clone())
+            .transfer(10u128.pow(20))
+            .nonrefundable_transfer(NEAR_BASE)
+            .actions()
+            .to_vec();
+        execute_transaction_from_actions(env, actions, &signer(), new_account_id.clone())
+            .unwrap()
+            .assert_success();
+        delete_account(env, &new_account, sender()).unwrap().assert_success();
+
+        assert!(state_keys(env) == baseline_keys, "state leaked in cycle {cycle}");
+        assert_eq!(env.query_account(sender()).storage_usage, baseline_storage_usage);
+    }
+}
+
+/// Deleting a sponsored account removes all of its state.
+#[test]
+fn sponsored_account_create_delete_cycles_do_not_leak_state() {
+    let mut env = setup_env();
+    create_delete_cycles(&mut env, 3);
+}
+
+/// Same as above but with many cycles; every cycle scans the whole trie.
+#[test]
+#[cfg_attr(not(feature = "expensive_tests"), ignore)]
+fn sponsored_account_many_create_delete_cycles_do_not_leak_state() {
+    let mut env = setup_env();
+    create_delete_cycles(&mut env, 100);
+}
+
+/// Both the creation and the deletion of an account with non-refundable storage
+/// show up in the account history.
+#[test]
+fn sponsored_account_history_contains_creation_and_deletion() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let new_account = InMemorySigner::from_seed(
+        new_account_id.clone(),
+        KeyType::ED25519,
+        new_account_id.as_str(),
+    );
+    let from_height = env.clients[0].chain.head().unwrap().height;
+    let create_account_tx_result = exec_transfers(
+        &mut env,
+        signer(),
+        new_account_id.clone(),
+        TransferConfig {
+            transfers: Transfers {
+                regular_amount: 10u128.pow(20),
+                nonrefundable_amount: NEAR_BASE,
+                nonrefundable_transfer_first: true,
+            },
+            account_creation: true,
+            implicit_account_creation: false,
+            deploy_contract: false,
+        },
+    )
+    .unwrap();
+    create_account_tx_result.assert_success();
+    let delete_account_tx_result = delete_account(&mut env, &new_account, receiver()).unwrap();
+    delete_account_tx_result.assert_success();
+    let to_height = env.clients[0].chain.head().unwrap().height;
+
+    let history = env.clients[0]
+        .chain
+        .transactions_affecting_account(&new_account_id, from_height, to_height)
+        .unwrap();
+    assert_eq!(
+        history,
+        vec![
+            create_account_tx_result.transaction_outcome.id,
+            delete_account_tx_result.transaction_outcome.id
+        ]
+    );
+}
+
+/// Regular transfer only reduces the total supply by the burnt gas.
+#[test]
+fn regular_transfer_is_supply_neutral() {
+    let mut env = setup_env();
+    let tx_result = exec_transfers(
+        &mut env,
+        signer(),
+        receiver(),
+        TransferConfig {
+            transfers: Transfers {
+                regular_amount: NEAR_BASE,
+                nonrefundable_amount: 0,
+                nonrefundable_transfer_first: false,
+            },
+            account_creation: false,
+            implicit_account_creation: false,
+            deploy_contract: false,
+        },
+    )
+    .unwrap();
+    tx_result.assert_success();
+    env.assert_supply_delta(tx_result.tokens_burnt(), 0);
+}
+
+/// Non-refundable balance cannot be transferred.
+#[test]
+fn non_refundable_balance_cannot_be_transferred() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let new_account = InMemorySigner::from_seed(
+        new_account_id.clone(),
+        KeyType::ED25519,
+        new_account_id.as_str(),
+    );
+    // The `new_account` is created with 1 NEAR non-refundable balance.
+    let create_account_tx_result = exec_transfers(
+        &mut env,
+        signer(),
+        new_account_id.clone(),
+        TransferConfig {
+            transfers: Transfers {
+                regular_amount: 0,
+                nonrefundable_amount: NEAR_BASE,
+                nonrefundable_transfer_first: true,
+            },
+            account_creation: true,
+            implicit_account_creation: false,
+            deploy_contract: false,
+        },
+    );
+    create_account_tx_result.unwrap().assert_success();
+
+    // Although `new_account` has 1 NEAR non-refundable balance, it cannot make neither refundable nor non-refundable transfer of 1 yoctoNEAR.
+    for nonrefundable in [false, true] {
+        let transfer_tx_result = exec_transfers(
+            &mut env,
+            new_account.clone(),
+            receiver(),
+            TransferConfig {
+                transfers: Transfers {
+                    regular_amount: if nonrefundable { 0 } else { 1 },
+                    nonrefundable_amount: if nonrefundable { 1 } else { 0 },
+                    nonrefundable_transfer_first: true,
+                },
+                account_creation: false,
+                implicit_account_creation: false,
+                deploy_contract: false,
+            },
+        );
+        match transfer_tx_result {
+            Err(InvalidTxError::NotEnoughBalance { signer_id, balance, .. }) => {
+                assert_eq!(signer_id, new_account_id);
+                assert_eq!(balance, 0);
+            }
+            _ => panic!("Expected NotEnoughBalance error"),
+        }
+    }
+}
+
+/// Actions of the self-operation that `create_operable_sponsored_account` funds.
+fn self_operation_actions() -> Vec<Action> {
+    vec![Action::Transfer(TransferAction { deposit: 0 })]
+}
+
+/// Creates a sponsored account with `nonrefundable_amount` non-refundable
+/// balance and just enough refundable balance to pay for one self-operation,
+/// i.e. one transaction with `self_operation_actions` sent to itself.
+fn create_operable_sponsored_account(
+    env: &mut TestEnv,
+    account_id: AccountId,
+    nonrefundable_amount: Balance,
+) -> InMemorySigner {
+    let account =
+        InMemorySigner::from_seed(account_id.clone(), KeyType::ED25519, account_id.as_str());
+    let head = env.clients[0].chain.head_header().unwrap();
+    let config = env.get_runtime_config(0, head.epoch_id().clone());
+    let self_operation =
+        env.tx_from_actions(self_operation_actions(), &account, account_id.clone());
+    let self_operation_cost = node_runtime::config::tx_cost(
+        &config,
+        &self_operation.transaction,
+        head.next_gas_price(),
+        true,
+    )
+    .unwrap()
+    .total_cost;
+
+    let actions = TransactionBuilder::new()
+        .create_account()
+        .add_key(account.public_key.clone())
+        .transfer(self_operation_cost)
+        .nonrefundable_transfer(nonrefundable_amount)
+        .actions()
+        .to_vec();
+    execute_transaction_from_actions(env, actions, &signer(), account_id).unwrap().assert_success();
+    account
+}
+
+/// An operable sponsored account can pay for exactly one self-operation.
+#[test]
+fn operable_sponsored_account_pays_for_one_self_operation() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let new_account =
+        create_operable_sponsored_account(&mut env, new_account_id.clone(), NEAR_BASE);
+
+    execute_transaction_from_actions(
+        &mut env,
+        self_operation_actions(),
+        &new_account,
+        new_account_id.clone(),
+    )
+    .unwrap()
+    .assert_success();
+    let account = env.query_account(new_account_id.clone());
+    assert_eq!(account.amount, 0);
+    assert_eq!(account.nonrefundable, NEAR_BASE);
+
+    let tx_result = execute_transaction_from_actions(
+        &mut env,
+        self_operation_actions(),
+        &new_account,
+        new_account_id.clone(),
+    );
+    match tx_result {
+        Err(InvalidTxError::NotEnoughBalance {
+            signer_id, balance, nonrefundable_balance, ..
+        }) => {
+            assert_eq!(signer_id, new_account_id);
+            assert_eq!(balance, 0);
+            // The non-refundable balance is reported, but ignored on purpose.
+            assert_eq!(nonrefundable_balance, NEAR_BASE);
+        }
+        _ => panic!("Expected NotEnoughBalance error"),
+    }
+}
+
+/// A sponsor with exactly enough refundable balance for a non-refundable
+/// transfer creating an implicit account pays the same at any gas price, up to
+/// the tokens burnt, which scale with the gas price.
+#[test]
+fn non_refundable_transfer_tokens_burnt_scale_with_gas_price() {
+    let base_gas_price = Genesis::test(vec![sender(), receiver()], 1).config.min_gas_price;
+    let tokens_burnt: Vec<Balance> = [base_gas_price, 2 * base_gas_price]
+        .into_iter()
+        .map(|gas_price| {
+            let mut env = setup_env_with_gas_price(gas_price);
+            let sponsor_id: AccountId = "sponsor.test0".parse().unwrap();
+            let sponsor = InMemorySigner::from_seed(
+                sponsor_id.clone(),
+                KeyType::ED25519,
+                sponsor_id.as_str(),
+            );
+            let implicit_account_id = derive_near_implicit_account_id(
+                PublicKey::from_seed(KeyType::ED25519, "implicit").unwrap_as_ed25519(),
+            );
+            let actions =
+                TransactionBuilder::new().nonrefundable_transfer(NEAR_BASE).actions().to_vec();
+
+            let head = env.clients[0].chain.head_header().unwrap();
+            assert_eq!(head.next_gas_price(), gas_price);
+            let config = env.get_runtime_config(0, head.epoch_id().clone());
+            let tx = env.tx_from_actions(actions.clone(), &sponsor, implicit_account_id.clone());
+            let sponsor_cost =
+                node_runtime::config::tx_cost(&config, &tx.transaction, gas_price, false)
+                    .unwrap()
+                    .total_cost;
+            let sponsor_actions = TransactionBuilder::new()
+                .create_account()
+                .add_key(sponsor.public_key.clone())
+                .transfer(sponsor_cost)
+                .nonrefundable_transfer(NEAR_BASE)
+                .actions()
+                .to_vec();
+            execute_transaction_from_actions(
+                &mut env,
+                sponsor_actions,
+                &signer(),
+                sponsor_id.clone(),
+            )
+            .unwrap()
+            .assert_success();
+
+            let tx_result =
+                execute_transaction_from_actions(&mut env, actions, &sponsor, implicit_account_id)
+                    .unwrap();
+            tx_result.assert_success();
+            let tokens_burnt = tx_result.tokens_burnt();
+            assert_eq!(
+                env.query_account(sponsor_id).amount,
+                sponsor_cost - NEAR_BASE - tokens_burnt
+            );
+            tokens_burnt
+        })
+        .collect();
+    assert_eq!(tokens_burnt[1], 2 * tokens_burnt[0]);
+}
+
+/// A contract from `FeeHelper::contract_for_storage_bytes` makes the storage
+/// usage of a new sponsored account exactly the target, so a non-refundable
+/// balance of exactly the storage cost of the target is enough, while one byte
+/// less is not.
+#[test]
+fn contract_for_storage_bytes_on_sponsored_account() {
+    let mut env = setup_env();
+    let target_storage_usage = 2000;
+    let head = env.clients[0].chain.head().unwrap();
+    let config = env.get_runtime_config(0, head.epoch_id);
+    let contract = fee_helper().contract_for_storage_bytes(target_storage_usage);
+
+    let new_account_id: AccountId = "short.test0".parse().unwrap();
+    let actions = sponsored_creation(
+        &new_account_id,
+        deposit_for_storage_bytes(target_storage_usage - 1, &config),
+    )
+    .deploy(contract.clone())
+    .actions()
+    .to_vec();
+    let tx_result =
+        execute_transaction_from_actions(&mut env, actions, &signer(), new_account_id.clone())
+            .unwrap();
+    let status = &tx_result.receipts_outcome[0].outcome.status;
+    assert!(matches!(
+        status,
+        ExecutionStatusView::Failure(TxExecutionError::ActionError(
+            ActionError { kind: ActionErrorKind::LackBalanceForState { account_id, .. }, .. }
+        )) if *account_id == new_account_id,
+    ));
+
+    let new_account_id: AccountId = "exact.test0".parse().unwrap();
+    env.create_and_verify_sponsored_account(
+        &signer(),
+        new_account_id.clone(),
+        deposit_for_storage_bytes(target_storage_usage, &config),
+        Some(contract),
+    )
+    .assert_all_actions_applied();
+    assert_eq!(env.query_storage_usage(new_account_id), target_storage_usage);
+}
+
+/// Non-refundable balance allows to have account with zero balance and more than 1kB of state.
+#[test]
+fn non_refundable_balance_allows_1kb_state_with_zero_balance() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let contract = near_test_contracts::sized_contract(1500);
+    let head = env.clients[0].chain.head().unwrap();
+    let config = env.get_runtime_config(0, head.epoch_id);
+    assert!(storage_bytes_covered(NEAR_BASE / 5, &config) >= 1500);
+    env.create_and_verify_sponsored_account(
+        &signer(),
+        new_account_id.clone(),
+        NEAR_BASE / 5,
+        Some(contract),
+    )
+    .assert_all_actions_applied();
+    assert!(env.query_account(new_account_id).storage_usage > 1000);
+}
+
+/// A contract sized by `FeeHelper::contract_for_nonrefundable` can be deployed
+/// on a zero balance account and uses up exactly the storage its deposit
+/// covers.
+#[test]
+fn sized_contract_exhausts_non_refundable_storage_coverage() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let fee_helper = fee_helper();
+    let nonrefundable = NEAR_BASE / 5;
+    let contract = fee_helper.contract_for_nonrefundable(nonrefundable);
+    env.create_and_verify_sponsored_account(
+        &signer(),
+        new_account_id.clone(),
+        nonrefundable,
+        Some(contract),
+    )
+    .assert_all_actions_applied();
+    let coverage = fee_helper.storage_coverage(&env.query_account(new_account_id));
+    assert_eq!(coverage.headroom(), 0);
+}
+
+/// Account creation with non-refundable transfer is rolled back atomically if
+/// the last action of the receipt fails.
+///
+/// Deploying invalid wasm succeeds on its own (the contract is only compiled
+/// when called), so the receipt ends with a function call on the freshly
+/// deployed code to make the final action fail.
+#[test]
+fn non_refundable_transfer_rolled_back_on_failing_last_action() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let sender_pre_balance = env.query_balance(sender());
+    let actions = vec![
+        Action::CreateAccount(CreateAccountAction {}),
+        Action::AddKey(Box::new(AddKeyAction {
+            public_key: PublicKey::from_seed(KeyType::ED25519, new_account_id.as_str()),
+            access_key: AccessKey { nonce: 0, permission: AccessKeyPermission::FullAccess },
+        })),
+        Action::NonrefundableStorageTransfer(NonrefundableStorageTransferAction {
+            deposit: NEAR_BASE,
+        }),
+        Action::DeployContract(DeployContractAction { code: vec![0u8; 100] }),
+        Action::FunctionCall(Box::new(FunctionCallAction {
+            method_name: "main".to_string(),
+            args: vec![],
+            gas: 10u64.pow(14),
+            deposit: 0,
+        })),
+    ];
+    let last_action_index = actions.len() as u64 - 1;
+    let tx_result =
+        execute_transaction_from_actions(&mut env, actions, &signer(), new_account_id.clone());
+    let outcome = tx_result.unwrap();
+    let status = &outcome.receipts_outcome[0].outcome.status;
+    assert!(matches!(
+        status,
+        ExecutionStatusView::Failure(TxExecutionError::ActionError(
+            ActionError { index: Some(index), kind: ActionErrorKind::FunctionCallError(_) }
+        )) if *index == last_action_index,
+    ));
+
+    // Neither the account nor its non-refundable balance survives the failed receipt,
+    // and the sender only pays for the burnt gas.
+    assert!(!account_exists(&mut env, new_account_id));
+    assert_eq!(env.query_balance(sender()), sender_pre_balance - outcome.tokens_burnt());
+}
+
+/// Non-refundable transfer creates a named account when blocks are produced by
+/// multiple validators.
+#[test]
+fn non_refundable_transfer_create_named_account_multiple_validators() {
+    let mut env = setup_env_with_validators(4);
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let actions = sponsored_creation_actions(&new_account_id, NEAR_BASE);
+    let outcome = execute_transaction_from_actions_on_all_clients(
+        &mut env,
+        actions,
+        &signer(),
+        new_account_id.clone(),
+    );
+    outcome.assert_success();
+    let new_account = env.query_account(new_account_id);
+    assert_eq!(new_account.amount, 0);
+    assert_eq!(new_account.nonrefundable, NEAR_BASE);
+}
+
+/// When the chunk is congested, the receipt creating a sponsored account is
+/// delayed and its actions can be inspected while it waits in the queue.
+#[test]
+fn delayed_actions_of_sponsored_creation() {
+    let mut env = setup_env();
+    let receiver_signer = InMemorySigner::from_seed(receiver(), KeyType::ED25519, "test1");
+    let actions = TransactionBuilder::new().deploy(near_test_contracts::rs_contract().to_vec());
+    execute_transaction_from_actions(
+        &mut env,
+        actions.actions().to_vec(),
+        &receiver_signer,
+        receiver(),
+    )
+    .unwrap()
+    .assert_success();
+
+    // Each call burns 300 Tgas, so only a few of them fit into one chunk and
+    // the remaining receipts, including the creation, are delayed.
+    let tip = env.clients[0].chain.head().unwrap();
+    let num_calls = 10;
+    for i in 0..num_calls {
+        let actions = vec![Action::FunctionCall(Box::new(FunctionCallAction {
+            method_name: "loop_forever".to_string(),
+            args: vec![],
+            gas: 300 * 10u64.pow(12),
+            deposit: 0,
+        }))];
+        let tx = SignedTransaction::from_actions(
+            tip.height + 1 + i,
+            sender(),
+            receiver(),
+            &signer(),
+            actions,
+            tip.last_block_hash,
+        );
+        assert_eq!(env.clients[0].process_tx(tx, false, false), ProcessTxResponse::ValidTx);
+    }
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let public_key = PublicKey::from_seed(KeyType::ED25519, new_account_id.as_str());
+    let creation_actions = TransactionBuilder::new()
+        .create_account()
+        .add_key(public_key)
+        .nonrefundable_transfer(NEAR_BASE)
+        .actions()
+        .to_vec();
+    let tx = SignedTransaction::from_actions(
+        tip.height + 1 + num_calls,
+        sender(),
+        new_account_id.clone(),
+        &signer(),
+        creation_actions.clone(),
+        tip.last_block_hash,
+    );
+    assert_eq!(env.clients[0].process_tx(tx, false, false), ProcessTxResponse::ValidTx);
+
+    let delayed_actions = (1..=5)
+        .find_map(|i| {
+            env.produce_block(0, tip.height + i);
+            let delayed_actions = env.query_pending_actions(&new_account_id);
+            (!delayed_actions.is_empty()).then_some(delayed_actions)
+        })
+        .expect("creation receipt was not delayed");
+    let receipt_id = delayed_actions[0].0;
+    assert!(delayed_actions.iter().all(|(id, _)| *id == receipt_id));
+    let actions: Vec<Action> = delayed_actions.into_iter().map(|(_, action)| action).collect();
+    assert_eq!(actions, creation_actions);
+    assert!(!account_exists(&mut env, new_account_id));
+}
+
+/// Gas used for sponsored creations is accounted to the shards of the new
+/// accounts, in the blocks in which the creations are executed.
+#[test]
+fn block_gas_usage_of_sponsored_creations_across_shards() {
+    let genesis = Genesis::test_sharded(Clock::real(), vec![sender(), receiver()], 1, vec![1, 1]);
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    let epoch_id = env.clients[0].chain.head().unwrap().epoch_id;
+    let epoch_manager = env.clients[0].epoch_manager.clone();
+    let num_shards = epoch_manager.shard_ids(&epoch_id).unwrap().len();
+    // One new account on every shard.
+    let mut new_accounts: Vec<(AccountId, ShardId)> = vec![];
+    for i in 0.. {
+        let account_id: AccountId = format!("subaccount{}.test0", i).parse().unwrap();
+        let shard_id = epoch_manager.account_id_to_shard_id(&account_id, &epoch_id).unwrap();
+        if new_accounts.iter().all(|(_, other_shard_id)| *other_shard_id != shard_id) {
+            new_accounts.push((account_id, shard_id));
+        }
+        if new_accounts.len() == num_shards {
+            break;
+        }
+    }
+
+    let tip = env.clients[0].chain.head().unwrap();
+    let mut tx_hashes = vec![];
+    for (i, (account_id, _)) in new_accounts.iter().enumerate() {
+        let tx = sponsored_creation(account_id, NEAR_BASE)
+            .build(&signer(), account_id.clone(), tip.height + 1 + i as u64, tip.last_block_hash)
+            .unwrap();
+        tx_hashes.push(tx.get_hash());
+        assert_eq!(env.clients[0].process_tx(tx, false, false), ProcessTxResponse::ValidTx);
+    }
+    for i in 0..5 {
+        env.produce_block(0, tip.height + 1 + i);
+    }
+
+    for ((account_id, shard_id), tx_hash) in new_accounts.into_iter().zip(tx_hashes) {
+        let tx_result = env.clients[0].chain.get_final_transaction_result(&tx_hash).unwrap();
+        tx_result.assert_success();
+        let creation = tx_result
+            .receipts_outcome
+            .iter()
+            .find(|receipt| receipt.outcome.executor_id == account_id)
+            .unwrap();
+        let gas_usage = env.block_gas_usage(&creation.block_hash);
+        assert_eq!(gas_usage.len(), num_shards);
+        let (_, shard_gas) = gas_usage.into_iter().find(|(id, _)| *id == shard_id).unwrap();
+        assert!(creation.outcome.gas_burnt > 0);
+        assert!(shard_gas >= creation.outcome.gas_burnt);
+    }
+}
+
+/// Non-refundable transfer successfully adds non-refundable balance when creating named account.
+#[test]
+fn non_refundable_transfer_create_named_account() {
+    let mut outcomes = vec![];
+    for_each_test_case(|env, index, transfers| {
+        let new_account_id: AccountId = format!("subaccount{index}.test0").parse().unwrap();
+        let outcome = exec_transfers(
+            env,
+            signer(),
+            new_account_id,
+            TransferConfig {
+                transfers,
+                account_creation: true,
+                implicit_account_creation: false,
+                deploy_contract: false,
+            },
+        )
+        .unwrap_or_else(|err| panic!("test case {index}: {err}"));
+        outcomes.push(outcome);
+    });
+    assert_all_success(&outcomes);
+    for outcome in &outcomes {
+        outcome.assert_valid_receipt_dag();
+    }
+}
+
+/// The tokens burnt by non-refundable transfers match the costs predicted by
+/// the `FeeHelper`, both when creating a named and an implicit account.
+#[test]
+fn non_refundable_transfer_cost_matches_tokens_burnt() {
+    let fee_helper = fee_helper();
+    let transfer_action_cost = fee_helper.gas_to_balance(
+        fee_helper.cfg().fee(ActionCosts::transfer).send_fee(false)
+            + fee_helper.cfg().fee(ActionCosts::transfer).exec_fee(),
+    );
+    // Cost of the `CreateAccount` and `AddKey` actions, without the receipt.
+    let create_account_with_key_cost =
+        fee_helper.create_account_transfer_full_key_cost() - fee_helper.transfer_cost();
+    for_each_test_case(|env, index, transfers| {
+        let new_account_id: AccountId = format!("subaccount{index}.test0").parse().unwrap();
+        let regular_amount = transfers.regular_amount;
+        let outcome = exec_transfers(
+            env,
+            signer(),
+            new_account_id,
+            TransferConfig {
+                transfers: transfers.clone(),
+                account_creation: true,
+                implicit_account_creation: false,
+                deploy_contract: false,
+            },
+        )
+        .unwrap_or_else(|err| panic!("test case {index}: {err}"));
+        let mut expected_cost =
+            create_account_with_key_cost + fee_helper.nonrefundable_transfer_cost();
+        if regular_amount > 0 {
+            expected_cost += transfer_action_cost;
+        }
+        assert_eq!(outcome.tokens_burnt(), expected_cost, "test case {index}");
+
+        if regular_amount == 0 {
+            let public_key = PublicKey::from_seed(KeyType::ED25519, &format!("implicit{index}"));
+            let outcome = exec_transfers(
+                env,
+                signer(),
+                derive_near_implicit_account_id(public_key.unwrap_as_ed25519()),
+                TransferConfig {
+                    transfers,
+                    account_creation: true,
+                    implicit_account_creation: true,
+                    deploy_contract: false,
+                },
+            )
+            .unwrap_or_else(|err| panic!("test case {index}: {err}"));
+            assert_eq!(
+                outcome.tokens_burnt(),
+                fee_helper.nonrefundable_transfer_cost_to_implicit_account(),
+                "test case {index}"
+            );
+        }
+    });
+}
+
+/// The costs computed by the `FeeHelper` for the protocol version activating
+/// non-refundable transfers match the golden values, so that fee changes for
+/// that version show up in review.
+#[test]
+fn fee_snapshot_at_non_refundable_balance_activation() {
+    let protocol_version = ProtocolFeature::NonRefundableBalance.protocol_version();
+    let config = RuntimeConfigStore::new(None).get_config(protocol_version).as_ref().clone();
+    let fee_helper = FeeHelper::new(config, 100_000_000);
+    let expected: BTreeMap<String, Balance> = [
+        ("add_key_full", 41964925000000000000),
+        ("create_account", 791611900000000000000),
+        ("create_account_transfer_full_key", 834989537500000000000),
+        ("delete_account", 51109700000000000000),
+        ("deploy_contract_per_byte", 7138594300000000),
+        ("nonrefundable_transfer", 44636512500000000000),
+        ("nonrefundable_transfer_to_implicit_account", 834989537500000000000),
+        ("transfer", 44636512500000000000),
+    ]
+    .into_iter()
+    .map(|(name, cost)| (name.to_string(), cost))
+    .collect();
+    assert_eq!(fee_helper.snapshot(), expected);
+}
+
+/// The owner of a NEAR-implicit account created by a non-refundable transfer
+/// can sign transactions spending the refundable balance of the account.
+#[test]
+fn implicit_account_owner_spends_refundable_balance() {
+    let mut env = setup_env();
+    let implicit_signer = create_implicit_test_signer(KeyType::ED25519, "implicit");
+    let implicit_account_id = implicit_signer.account_id.clone();
+    let nonrefundable_amount = NEAR_BASE;
+    let regular_amount = 10 * NEAR_BASE;
+    for actions in [
+        TransactionBuilder::new().nonrefundable_transfer(nonrefundable_amount),
+        TransactionBuilder::new().transfer(regular_amount),
+    ] {
+        execute_transaction_from_actions(
+            &mut env,
+            actions.actions().to_vec(),
+            &signer(),
+            implicit_account_id.clone(),
+        )
+        .unwrap()
+        .assert_success();
+    }
+
+    let transfer_amount = NEAR_BASE;
+    let receiver_pre_balance = env.query_balance(receiver());
+    let actions = TransactionBuilder::new().transfer(transfer_amount).actions().to_vec();
+    execute_transaction_from_actions(&mut env, actions, &implicit_signer, receiver())
+        .unwrap()
+        .assert_success();
+    assert_eq!(env.query_balance(receiver()), receiver_pre_balance + transfer_amount);
+    let account = env.query_account(implicit_account_id);
+    assert_eq!(account.nonrefundable, nonrefundable_amount);
+    assert!(account.amount < regular_amount - transfer_amount);
+}
+
+/// The non-refundable balance is in place right in the block that executed the
+/// account creation, not only eventually.
+#[test]
+fn non_refundable_balance_right_after_account_creation() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let nonrefundable_amount = NEAR_BASE;
+    let actions = sponsored_creation_actions(&new_account_id, nonrefundable_amount);
+    let tx_result =
+        execute_transaction_from_actions(&mut env, actions, &signer(), new_account_id.clone())
+            .unwrap();
+    tx_result.assert_success();
+
+    let account = env.account_view_after_tx(new_account_id, &tx_result.transaction.hash);
+    assert_eq!(account.amount, 0);
+    assert_eq!(account.nonrefundable, nonrefundable_amount);
+    assert!(!account.has_contract());
+}
+
+/// Querying an account at a block height shows its non-refundable balance as
+/// of that block: before the sponsored creation the account does not exist
+/// and from the creation block on it holds the non-refundable balance.
+#[test]
+fn query_non_refundable_balance_at_block_height() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let nonrefundable_amount = NEAR_BASE;
+    let actions = sponsored_creation_actions(&new_account_id, nonrefundable_amount);
+    let tx_result =
+        execute_transaction_from_actions(&mut env, actions, &signer(), new_account_id.clone())
+            .unwrap();
+    tx_result.assert_success();
+    let creation_block_hash = tx_result.receipts_outcome[0].block_hash;
+    let creation_height =
+        env.clients[0].chain.get_block_header(&creation_block_hash).unwrap().height();
+
+    let mut query_at = |height| {
+        let request = QueryRequest::ViewAccount { account_id: new_account_id.clone() };
+        let block_reference = BlockReference::BlockId(BlockId::Height(height));
+        env.query_view_at(Some(block_reference), request).map(|response| match response.kind {
+            QueryResponseKind::ViewAccount(view) => view,
+            _ => panic!("wrong query response"),
+        })
+    };
+    assert!(matches!(query_at(creation_height - 1), Err(QueryError::UnknownAccount { .. })));
+    let account = query_at(creation_height).unwrap();
+    assert_eq!(account.amount, 0);
+    assert_eq!(account.nonrefundable, nonrefundable_amount);
+    assert!(matches!(query_at(creation_height + 1000), Err(QueryError::UnknownBlock { .. })));
+}
+
+/// Non-refundable transfer must be the only action in an implicit account
+/// creation transaction, otherwise the transaction is rejected at validation
+/// without creating the account or burning any gas.
+fn assert_rejected_implicit_account_creation(
+    env: &mut TestEnv,
+    tx_result: Result<FinalExecutionOutcomeView, InvalidTxError>,
+    account_id: AccountId,
+    sender_pre_balance: Balance,
+) {
+    assert_eq!(
+        tx_result,
+        Err(InvalidTxError::ActionsValidation(
+            ActionsValidationError::NonrefundableTransferToImplicitAccountMustBeOnlyAction {
+                receiver_id: account_id.clone(),
+            }
+        ))
+    );
+    assert!(!account_exists(env, account_id));
+    assert_eq!(env.query_balance(sender()), sender_pre_balance);
+}
+
+/// The sender balance delta derived from the outcome of a sponsored creation
+/// matches the observed change of the sender balance.
+#[test]
+fn sponsored_account_creation_sender_balance_delta() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let actions = TransactionBuilder::new()
+        .create_account()
+        .add_key(PublicKey::from_seed(KeyType::ED25519, new_account_id.as_str()))
+        .transfer(NEAR_BASE / 10)
+        .nonrefundable_transfer(NEAR_BASE)
+        .actions()
+        .to_vec();
+    let sender_pre_balance = env.query_balance(sender());
+    let tx_result =
+        execute_transaction_from_actions(&mut env, actions, &signer(), new_account_id).unwrap();
+    tx_result.assert_success();
+
+    let delta = tx_result.sender_balance_delta();
+    assert!(delta < -((NEAR_BASE + NEAR_BASE / 10) as i128));
+    assert_eq!(env.query_balance(sender()) as i128, sender_pre_balance as i128 + delta);
+}
+
+/// For an account whose storage is only partially paid by its non-refundable
+/// balance, the rest of the storage is paid by its refundable balance, which
+/// can not be withdrawn below that floor.
+#[test]
+fn partially_sponsored_account_refundable_storage_floor() {
+    let mut env = setup_env();
+    let fee_helper = fee_helper();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let nonrefundable_amount = NEAR_BASE / 10;
+    let regular_amount = 10 * NEAR_BASE;
+    let actions = TransactionBuilder::new()
+        .create_account()
+        .add_key(PublicKey::from_seed(KeyType::ED25519, new_account_id.as_str()))
+        .transfer(regular_amount)
+        .nonrefundable_transfer(nonrefundable_amount)
+        .deploy(near_test_contracts::rs_contract().to_vec())
+        .actions()
+        .to_vec();
+    execute_transaction_from_actions(&mut env, actions, &signer(), new_account_id.clone())
+        .unwrap()
+        .assert_success();
+
+    let account = env.query_account(new_account_id);
+    let storage_cost =
+        account.storage_usage as Balance * fee_helper.rt_cfg.storage_amount_per_byte();
+    assert!(storage_cost > nonrefundable_amount, "contract is covered by non-refundable balance");
+    let floor = fee_helper.refundable_storage_floor(&account);
+    assert!(floor > 0);
+    assert!(floor < account.amount);
+    assert_eq!(floor, storage_cost - nonrefundable_amount);
+}
+
+/// Returns the JSON representation of the `ViewAccount` query response.
+fn view_account_json(env: &mut TestEnv, account_id: AccountId) -> serde_json::Value {
+    match env.query_view(QueryRequest::ViewAccount { account_id }).unwrap().kind {
+        QueryResponseKind::ViewAccount(view) => serde_json::to_value(view).unwrap(),
+        _ => panic!("wrong query response"),
+    }
+}
+
+/// The account view returned by queries contains the non-refundable balance as
+/// a decimal string, next to the refundable `amount` and the `total_balance`.
+/// Older clients may omit it, in which case it defaults to zero.
+#[test]
+fn view_account_json_contains_non_refundable_balance() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    env.create_and_verify_sponsored_account(&signer(), new_account_id.clone(), NEAR_BASE, None);
+    let json = view_account_json(&mut env, new_account_id);
+    assert_eq!(json["amount"], "0");
+    assert_eq!(json["nonrefundable"], NEAR_BASE.to_string());
+    assert_eq!(json["total_balance"], NEAR_BASE.to_string());
+    let view: AccountView = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(view.nonrefundable, NEAR_BASE);
+
+    let mut json = json.as_object().unwrap().clone();
+    json.remove("nonrefundable");
+    let view: AccountView = serde_json::from_value(json.into()).unwrap();
+    assert_eq!(view.nonrefundable, 0);
+}
+
+/// Accounts created before non-refundable transfers were enabled serialize a
+/// zero non-refundable balance instead of omitting it.
+#[test]
+fn view_account_json_of_account_created_before_feature() {
+    let mut env = setup_env_with_protocol_version(Some(
+        ProtocolFeature::NonRefundableBalance.protocol_version() - 1,
+    ));
+    let json = view_account_json(&mut env, sender());
+    assert_eq!(json["nonrefundable"], "0");
+    let balance = |field: &str| json[field].as_str().unwrap().parse::<Balance>().unwrap();
+    assert_eq!(balance("total_balance"), balance("amount") + balance("locked"));
+}
+
+/// Viewing several accounts at once returns them in the requested order, with
+/// an error entry for every account that does not exist.
+#[test]
+fn view_accounts_reports_missing_accounts_in_order() {
+    let mut env = setup_env();
+    let sponsored_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let missing_account_id: AccountId = "missing.test0".parse().unwrap();
+    env.create_and_verify_sponsored_account(
+        &signer(),
+        sponsored_account_id.clone(),
+        NEAR_BASE,
+        None,
+    );
+
+    let account_ids = vec![
+        missing_account_id.clone(),
+        sponsored_account_id.clone(),
+        sender(),
+        missing_account_id.clone(),
+    ];
+    let request = QueryRequest::ViewAccounts { account_ids: account_ids.clone() };
+    let accounts = match env.query_view(request).unwrap().kind {
+        QueryResponseKind::Accounts(accounts) => accounts,
+        _ => panic!("wrong query response"),
+    };
+    assert_eq!(
+        accounts.iter().map(|result| result.account_id.clone()).collect::<Vec<_>>(),
+        account_ids
+    );
+    for missing in [&accounts[0], &accounts[3]] {
+        assert_eq!(missing.account, None);
+        assert!(missing.error.as_ref().unwrap().contains(missing_account_id.as_str()));
+    }
+    assert_eq!(accounts[1].error, None);
+    assert_eq!(accounts[1].account.as_ref().unwrap().nonrefundable, NEAR_BASE);
+    assert_eq!(accounts[2].error, None);
+    assert_eq!(accounts[2].account, Some(env.query_account(sender())));
+}
+
+/// A transaction runs to completion through a chain of receipts: the account
+/// creation with a transfer and a function call, the promise spawned by the
+/// call, and the refunds.
+#[test]
+fn execute_receipt_chain_to_completion() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let args = serde_json::json!([{"create": {
+        "account_id": new_account_id.as_str(),
+        "method_name": "log_something",
+        "arguments": [],
+        "amount": "0",
+        "gas": 10u64.pow(13),
+    }, "id": 0}]);
+    let mut actions = TransactionBuilder::new()
+        .create_account()
+        .add_key(PublicKey::from_seed(KeyType::ED25519, new_account_id.as_str()))
+        .transfer(10 * NEAR_BASE)
+        .deploy(near_test_contracts::rs_contract().to_vec())
+        .actions()
+        .to_vec();
+    actions.push(Action::FunctionCall(Box::new(FunctionCallAction {
+        method_name: "call_promise".to_string(),
+        args: serde_json::to_vec(&args).unwrap(),
+        gas: 10u64.pow(14),
+        deposit: 0,
+    })));
+    let tx = env.tx_from_actions(actions, &signer(), new_account_id.clone());
+    let tx_result = env.execute_and_run_to_completion(tx).unwrap();
+    tx_result.assert_success();
+    tx_result.assert_logs_eq(&["hello"]);
+    let promise_outcome =
+        tx_result.receipts_outcome.iter().find(|outcome| !outcome.outcome.logs.is_empty()).unwrap();
+    assert_eq!(promise_outcome.outcome.executor_id, new_account_id);
+    assert!(!promise_outcome.outcome.receipt_ids.is_empty(), "no refund of the promise");
+    // The effects of all receipts, including the gas reward of the calls, are
+    // visible to queries right away.
+    let account = env.query_account(new_account_id);
+    assert!(account.amount > 10 * NEAR_BASE);
+}
+
+/// A relayer can onboard a new user by relaying a sponsored creation signed by
+/// the user in a meta transaction.
+///
+/// The relayer pays for all gas, and, as for any delegate action, also for the
+/// deposits, including the non-refundable one. The user balance is untouched.
+#[test]
+fn non_refundable_transfer_create_account_in_meta_transaction() {
+    let mut env = setup_env();
+    let relayer = receiver();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let nonrefundable_amount = NEAR_BASE;
+    let actions = sponsored_creation_actions(&new_account_id, nonrefundable_amount);
+    let sender_pre_balance = env.query_balance(sender());
+    let relayer_pre_balance = env.query_balance(relayer.clone());
+
+    let tx = env.meta_tx_from_actions(actions, sender(), relayer.clone(), new_account_id.clone());
+    let tx_result = env.execute_tx(tx).unwrap();
+    tx_result.assert_all_actions_applied();
+    // Let the gas refunds to the relayer be applied.
+    let height = env.clients[0].chain.head().unwrap().height;
+    for i in 0..2 {
+        env.produce_block(0, height + 1 + i);
+    }
+
+    let gas_cost = tx_result.tokens_burnt();
+    assert!(gas_cost > 0);
+    assert_eq!(env.query_balance(relayer), relayer_pre_balance - nonrefundable_amount - gas_cost);
+    assert_eq!(env.query_balance(sender()), sender_pre_balance);
+    let new_account = env.query_account(new_account_id);
+    assert_eq!(new_account.amount, 0);
+    assert_eq!(new_account.nonrefundable, nonrefundable_amount);
+}
+
+/// A non-refundable transfer to an existing account is rejected also when it is
+/// relayed in a meta transaction, with the error of the inner receipt as the
+/// final status.
+///
+/// The relayer pays for the gas of the attempted execution. As for any failed
+/// delegate action, the deposit is refunded to the sender of the delegate
+/// action, not to the relayer.
+#[test]
+fn reject_non_refundable_transfer_existing_account_in_meta_transaction() {
+    let mut env = setup_env();
+    let relayer = receiver();
+    let nonrefundable_amount = NEAR_BASE;
+    let actions = vec![Action::NonrefundableStorageTransfer(NonrefundableStorageTransferAction {
+        deposit: nonrefundable_amount,
+    })];
+    let sender_pre_balance = env.query_balance(sender());
+    let relayer_before = env.query_account(relayer.clone());
+
+    let tx = env.meta_tx_from_actions(actions, sender(), relayer.clone(), relayer.clone());
+    let tx_result = env.execute_tx(tx).unwrap();
+    assert_eq!(
+        tx_result.status,
+        FinalExecutionStatus::Failure(TxExecutionError::ActionError(ActionError {
+            index: Some(0),
+            kind: ActionErrorKind::NonRefundableBalanceToExistingAccount {
+                account_id: relayer.clone()
+            },
+        }))
+    );
+    env.assert_gas_burnt_positive(&tx_result);
+    // Let the refunds be applied.
+    let height = env.clients[0].chain.head().unwrap().height;
+    for i in 0..2 {
+        env.produce_block(0, height + 1 + i);
+    }
+
+    let relayer_after = env.query_account(relayer);
+    assert_eq!(
+        relayer_after.amount,
+        relayer_before.amount - nonrefundable_amount - tx_result.tokens_burnt()
+    );
+    assert_eq!(relayer_after.nonrefundable, relayer_before.nonrefundable);
+    assert_eq!(env.query_balance(sender()), sender_pre_balance + nonrefundable_amount);
+}
+
+/// The only refund of a sponsored creation returns to the signer the gas
+/// overpaid for the creation receipt, at the pessimistically inflated gas price,
+/// compared to the price at which it was executed.
+#[test]
+fn sponsored_creation_gas_refund() {
+    let mut env = setup_env();
+    let outcome = env.create_and_verify_sponsored_account(
+        &signer(),
+        "subaccount.test0".parse().unwrap(),
+        NEAR_BASE,
+        None,
+    );
+    let creation_outcome = outcome.receipts_outcome[0].outcome.clone();
+    let creation_receipt_id = outcome.receipts_outcome[0].id;
+    let outcome = env.clients[0].chain.get_final_transaction_result_with_receipt(outcome).unwrap();
+    let creation_receipt =
+        outcome.receipts.iter().find(|receipt| receipt.receipt_id == creation_receipt_id).unwrap();
+    let ReceiptEnumView::Action { gas_price, .. } = creation_receipt.receipt else {
+        panic!("creation receipt is not an action receipt");
+    };
+    assert_eq!(
+        outcome.refunds(),
+        vec![RefundView {
+            receiver_id: sender(),
+            amount: gas_price * creation_outcome.gas_burnt as Balance
+                - creation_outcome.tokens_burnt,
+            is_gas_refund: true,
+        }]
+    );
+}
+
+/// The gas price only depends on the gas used by previous blocks, not on the
+/// deposits of the included transactions.
+///
+/// Sponsored creations with small and large non-refundable deposits burn the
+/// same gas. With a low gas limit they fill the chunks enough to raise the gas
+/// price, which must evolve in the same way for both deposits.
+#[test]
+fn non_refundable_deposit_does_not_affect_gas_price() {
+    let num_creations = 20;
+    let num_blocks = 10;
+    let gas_prices: Vec<Vec<Balance>> = [1, NEAR_BASE]
+        .into_iter()
+        .map(|deposit| {
+            let mut genesis = Genesis::test(vec![sender(), receiver()], 1);
+            genesis.config.gas_limit = 10u64.pow(12);
+            let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+            let tip = env.clients[0].chain.head().unwrap();
+            for i in 0..num_creations {
+                let new_account_id: AccountId = format!("sub{i}.{}", sender()).parse().unwrap();
+                let actions = sponsored_creation_actions(&new_account_id, deposit);
+                let tx = SignedTransaction::from_actions(
+                    tip.height + 1 + i,
+                    sender(),
+                    new_account_id,
+                    &signer(),
+                    actions,
+                    tip.last_block_hash,
+                );
+                assert_eq!(env.clients[0].process_tx(tx, false, false), ProcessTxResponse::ValidTx);
+            }
+            let initial_gas_price = env.clients[0].chain.head_header().unwrap().next_gas_price();
+            std::iter::once(initial_gas_price)
+                .chain((1..=num_blocks).map(|i| {
+                    env.produce_block(0, tip.height + i);
+                    env.clients[0].chain.head_header().unwrap().next_gas_price()
+                }))
+                .collect()
+        })
+        .collect();
+    assert!(
+        gas_prices[0].iter().any(|gas_price| *gas_price > gas_prices[0][0]),
+        "gas price did not increase: {:?}",
+        gas_prices[0]
+    );
+    assert_eq!(gas_prices[0], gas_prices[1]);
+}
+
+/// Sponsored creations submitted together are executed in a deterministic
+/// order, the order of the transactions given by their nonces.
+///
+/// A transaction can only create a single account, so a batch of creations is
+/// submitted as consecutive transactions of the same signer. Creations do not
+/// emit events, so the order is read from the order of the outcomes of each
+/// applied chunk.
+#[test]
+fn sponsored_creations_executed_in_submission_order() {
+    let account_ids: Vec<AccountId> = ["a", "b", "c"]
+        .iter()
+        .map(|name| format!("{name}.{}", sender()).parse().unwrap())
+        .collect();
+    for _ in 0..3 {
+        let mut env = setup_env();
+        let tip = env.clients[0].chain.head().unwrap();
+        for (nonce, account_id) in (tip.height + 1..).zip(&account_ids) {
+            let actions = sponsored_creation_actions(account_id, NEAR_BASE);
+            let tx = SignedTransaction::from_actions(
+                nonce,
+                sender(),
+                account_id.clone(),
+                &signer(),
+                actions,
+                tip.last_block_hash,
+            );
+            assert_eq!(env.clients[0].process_tx(tx, false, false), ProcessTxResponse::ValidTx);
+        }
+
+        let mut created = vec![];
+        for i in 1..=5 {
+            env.produce_block(0, tip.height + i);
+            let chain = &env.clients[0].chain;
+            let block_hash = chain.head().unwrap().last_block_hash;
+            let outcome_ids = chain
+                .chain_store()
+                .get_outcomes_by_block_hash_and_shard_id(&block_hash, 0)
+                .unwrap();
+            for outcome_id in outcome_ids {
+                let outcome = chain.get_execution_outcome(&outcome_id).unwrap();
+                let executor_id = outcome.outcome_with_id.outcome.executor_id;
+                if account_ids.contains(&executor_id) {
+                    created.push(executor_id);
+                }
+            }
+        }
+        assert_eq!(created, account_ids);
+    }
+}
+
+/// Resubmitting a sponsored creation is rejected and does not fund the new
+/// account twice.
+#[test]
+fn resubmitted_sponsored_creation_is_rejected() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let actions = sponsored_creation_actions(&new_account_id, NEAR_BASE);
+    let tx = env.tx_from_actions(actions, &signer(), new_account_id.clone());
+    env.execute_tx(tx.clone()).unwrap().assert_success();
+    let height = env.clients[0].chain.head().unwrap().height;
+    for i in 0..2 {
+        env.produce_block(0, height + 1 + i);
+    }
+    let sender_before = env.query_account(sender());
+    let new_account_before = env.query_account(new_account_id.clone());
+
+    env.assert_duplicate_rejected(tx);
+    for i in 2..4 {
+        env.produce_block(0, height + 1 + i);
+    }
+    env.assert_account_unchanged(sender(), &sender_before);
+    env.assert_account_unchanged(new_account_id, &new_account_before);
+}
+
+/// A fully sponsored account covers its storage, but would not do so without
+/// the non-refundable balance.
+#[test]
+fn sponsored_account_storage_coverage() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let actions = sponsored_creation_actions(&new_account_id, NEAR_BASE);
+    execute_transaction_from_actions(&mut env, actions, &signer(), new_account_id.clone())
+        .unwrap()
+        .assert_success();
+    let fee_helper = fee_helper();
+
+    let mut account = env.query_account(new_account_id);
+    let coverage = fee_helper.storage_coverage(&account);
+    assert!(coverage.is_funded());
+    assert!(coverage.ratio() >= 1.0);
+
+    account.nonrefundable = 0;
+    let coverage = fee_helper.storage_coverage(&account);
+    assert!(!coverage.is_funded());
+    assert!(coverage.ratio() < 1.0);
+}
+
+/// A sponsored account funded with exactly its storage cost has no storage
+/// headroom left, while an over-funded one can still grow.
+#[test]
+fn sponsored_account_storage_headroom() {
+    let mut env = setup_env();
+    let fee_helper = fee_helper();
+    let create_account = |env: &mut TestEnv, account_id: &AccountId, nonrefundable_amount| {
+        let actions = sponsored_creation_actions(account_id, nonrefundable_amount);
+        execute_transaction_from_actions(env, actions, &signer(), account_id.clone())
+            .unwrap()
+            .assert_success();
+        fee_helper.storage_coverage(&env.query_account(account_id.clone()))
+    };
+
+    let over_funded_id: AccountId = "overfunded.test0".parse().unwrap();
+    let over_funded = create_account(&mut env, &over_funded_id, NEAR_BASE);
+    assert!(over_funded.headroom() > 0);
+
+    // Both accounts hold a single key of the same type, so they use the same
+    // number of bytes.
+    let exactly_funded_id: AccountId = "exactlyfunded.test0".parse().unwrap();
+    let storage_cost =
+        over_funded.used_bytes as Balance * fee_helper.rt_cfg.storage_amount_per_byte();
+    let exactly_funded = create_account(&mut env, &exactly_funded_id, storage_cost);
+    assert_eq!(exactly_funded.used_bytes, over_funded.used_bytes);
+    assert_eq!(exactly_funded.headroom(), 0);
+}
+
+/// A sponsored account is stored as a V2 record holding the non-refundable
+/// balance.
+#[test]
+fn sponsored_account_record() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let actions = sponsored_creation_actions(&new_account_id, NEAR_BASE);
+    execute_transaction_from_actions(&mut env, actions, &signer(), new_account_id.clone())
+        .unwrap()
+        .assert_success();
+
+    let account = env.query_account_raw(new_account_id);
+    assert_eq!(account.version(), AccountVersion::V2);
+    assert_eq!(account.nonrefundable(), NEAR_BASE);
+    assert_eq!(account.amount(), 0);
+}
+
+/// `order_actions` puts the account creation first, then the transfers in the
+/// configured order, then the deployment.
+#[test]
+fn order_actions_for_test_cases() {
+    let named_account: AccountId = "subaccount.test0".parse().unwrap();
+    let implicit_account = derive_near_implicit_account_id(
+        PublicKey::from_seed(KeyType::ED25519, "near").unwrap_as_ed25519(),
     );
-    create_account_tx_result.unwrap().assert_success();
+    for transfers in TEST_CASES {
+        for (receiver, implicit_account_creation) in
+            [(&named_account, false), (&implicit_account, true)]
+        {
+            for deploy_contract in [false, true] {
+                let config = TransferConfig {
+                    transfers: transfers.clone(),
+                    account_creation: true,
+                    implicit_account_creation,
+                    deploy_contract,
+                };
+                let actions = order_actions(receiver, &config);
+                let kinds: Vec<&str> = actions.iter().map(|action| action.as_ref()).collect();
 
-    // Although `new_account` has 1 NEAR non-refundable balance, it cannot make neither refundable nor non-refundable transfer of 1 yoctoNEAR.
-    for nonrefundable in [false, true] {
-        let transfer_tx_result = exec_transfers(
-            &mut env,
-            new_account.clone(),
-            receiver(),
-            TransferConfig {
-                transfers: Transfers {
-                    regular_amount: if nonrefundable { 0 } else { 1 },
-                    nonrefundable_amount: if nonrefundable { 1 } else { 0 },
-                    nonrefundable_transfer_first: true,
+                let mut expected = vec![];
+                if !implicit_account_creation {
+                    expected.extend(["CreateAccount", "AddKey"]);
+                }
+                match (transfers.nonrefundable_transfer_first, transfers.regular_amount > 0) {
+                    (_, false) => expected.push("NonrefundableStorageTransfer"),
+                    (true, true) => expected.extend(["NonrefundableStorageTransfer", "Transfer"]),
+                    (false, true) => expected.extend(["Transfer", "NonrefundableStorageTransfer"]),
+                }
+                if deploy_contract {
+                    expected.push("DeployContract");
+                }
+                assert_eq!(kinds, expected, "{config:?}");
+            }
+        }
+    }
+}
+
+/// A sponsored account can only be created by its direct parent, so creating
+/// a grandchild whose parent does not exist fails without creating anything.
+#[test]
+fn non_refundable_transfer_create_grandchild_account() {
+    let mut env = setup_env();
+    let parent_id: AccountId = "account.test0".parse().unwrap();
+    let new_account_id: AccountId = "sub.account.test0".parse().unwrap();
+    let sender_pre_balance = env.query_balance(sender());
+    let actions = sponsored_creation_actions(&new_account_id, NEAR_BASE);
+    let tx_result =
+        execute_transaction_from_actions(&mut env, actions, &signer(), new_account_id.clone())
+            .unwrap();
+
+    let status = &tx_result.receipts_outcome[0].outcome.status;
+    assert!(matches!(
+        status,
+        ExecutionStatusView::Failure(TxExecutionError::ActionError(
+            ActionError { index: Some(0), kind: ActionErrorKind::CreateAccountNotAllowed { account_id, predecessor_id } }
+        )) if *account_id == new_account_id && *predecessor_id == sender(),
+    ));
+    assert!(!account_exists(&mut env, new_account_id));
+    assert!(!account_exists(&mut env, parent_id));
+    // Only gas is paid, the non-refundable deposit is refunded.
+    assert_eq!(env.query_balance(sender()), sender_pre_balance - tx_result.tokens_burnt());
+}
+
+/// A sponsored account creation built against a block older than the
+/// transaction validity period is rejected without charging the sender.
+#[test]
+fn reject_expired_non_refundable_transfer() {
+    let validity_period = 5;
+    let mut env = setup_env_with_transaction_validity_period(validity_period);
+    let tip = env.clients[0].chain.head().unwrap();
+    for i in 1..=validity_period + 1 {
+        env.produce_block(0, tip.height + i);
+    }
+
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let sender_before = env.query_account(sender());
+    let actions = sponsored_creation_actions(&new_account_id, NEAR_BASE);
+    let nonce = get_nonce(&mut env, &signer());
+    let tx = SignedTransaction::from_actions(
+        nonce + 1,
+        sender(),
+        new_account_id.clone(),
+        &signer(),
+        actions,
+        tip.last_block_hash,
+    );
+    let tx_result = env.execute_tx(tx);
+    assert!(matches!(tx_result, Err(InvalidTxError::Expired)), "{tx_result:?}");
+    env.assert_no_gas_burnt_on_rejection(&tx_result, sender(), &sender_before);
+    assert!(!account_exists(&mut env, new_account_id));
+}
+
+/// The receipts of a transaction creating an ETH-implicit account with a
+/// non-refundable transfer report the id of the created account.
+#[test]
+fn non_refundable_transfer_reports_created_eth_implicit_account() {
+    let mut env = setup_env();
+    let public_key = PublicKey::from_seed(KeyType::SECP256K1, "created");
+    let new_account_id = derive_eth_implicit_account_id(public_key.unwrap_as_secp256k1());
+    let actions = TransactionBuilder::new().nonrefundable_transfer(NEAR_BASE).actions().to_vec();
+    let tx_result =
+        execute_transaction_from_actions(&mut env, actions, &signer(), new_account_id).unwrap();
+    tx_result.assert_success();
+
+    let tx_result =
+        env.clients[0].chain.get_final_transaction_result_with_receipt(tx_result).unwrap();
+    assert_eq!(
+        tx_result.created_account_ids(),
+        vec![derive_eth_implicit_account_id(public_key.unwrap_as_secp256k1())]
+    );
+}
+
+/// Non-refundable transfer successfully adds non-refundable balance when
+/// creating an implicit account of any kind.
+#[test]
+fn non_refundable_transfer_create_implicit_account() {
+    for_each_implicit_key_type(|key_type, derive_account_id| {
+        for_each_test_case(|env, index, transfers| {
+            let public_key = PublicKey::from_seed(key_type, &format!("{key_type}{index}"));
+            let new_account_id = derive_account_id(&public_key);
+            let sender_pre_balance = env.query_balance(sender());
+            let regular_amount = transfers.regular_amount;
+            let tx_result = exec_transfers(
+                env,
+                signer(),
+                new_account_id.clone(),
+                TransferConfig {
+                    transfers,
+                    account_creation: true,
+                    implicit_account_creation: true,
+                    deploy_contract: false,
                 },
-                account_creation: false,
-                implicit_account_creation: false,
-                deploy_contract: false,
-            },
-        );
-        match transfer_tx_result {
-            Err(InvalidTxError::NotEnoughBalance { signer_id, balance, .. }) => {
-                assert_eq!(signer_id, new_account_id);
-                assert_eq!(balance, 0);
+            );
+            if regular_amount == 0 {
+                let outcome =
+                    tx_result.unwrap_or_else(|err| panic!("{key_type} test case {index}: {err}"));
+                outcome.assert_all_actions_applied();
+                outcome.assert_valid_receipt_dag();
+            } else {
+                assert_rejected_implicit_account_creation(
+                    env,
+                    tx_result,
+                    new_account_id,
+                    sender_pre_balance,
+                );
             }
-            _ => panic!("Expected NotEnoughBalance error"),
+        });
+    });
+}
+
+/// Mixing a non-refundable transfer with a regular transfer to an implicit
+/// account is rejected during actions validation, so the transaction never
+/// enters the pool and no receipt is spawned for it.
+#[test]
+fn mixed_non_refundable_transfer_to_implicit_account_rejected_before_pool() {
+    for_each_implicit_key_type(|key_type, derive_account_id| {
+        let mut env = setup_env();
+        let public_key = PublicKey::from_seed(key_type, "mixed");
+        let new_account_id = derive_account_id(&public_key);
+        let actions = vec![
+            Action::Transfer(TransferAction { deposit: 1 }),
+            Action::NonrefundableStorageTransfer(NonrefundableStorageTransferAction {
+                deposit: NEAR_BASE,
+            }),
+        ];
+        let sender_pre_balance = env.query_balance(sender());
+        let tx = env.tx_from_actions(actions, &signer(), new_account_id.clone());
+        assert_eq!(
+            env.clients[0].process_tx(tx, false, false),
+            ProcessTxResponse::InvalidTx(InvalidTxError::ActionsValidation(
+                ActionsValidationError::NonrefundableTransferToImplicitAccountMustBeOnlyAction {
+                    receiver_id: new_account_id.clone(),
+                }
+            ))
+        );
+        assert_eq!(env.clients[0].sharded_tx_pool.len(ShardUId::single_shard()), 0);
+
+        let tip = env.clients[0].chain.head().unwrap();
+        for i in 1..=3 {
+            env.produce_block(0, tip.height + i);
         }
+        assert!(!account_exists(&mut env, new_account_id));
+        assert_eq!(env.query_balance(sender()), sender_pre_balance);
+    });
+}
+
+/// A non-refundable transfer with zero deposit is rejected before it gets into
+/// the pool, so no gas is burnt on a no-op sponsorship.
+#[test]
+fn zero_non_refundable_transfer_rejected_before_pool() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let actions = vec![
+        Action::CreateAccount(CreateAccountAction {}),
+        Action::NonrefundableStorageTransfer(NonrefundableStorageTransferAction { deposit: 0 }),
+    ];
+    let tx = env.tx_from_actions(actions, &signer(), new_account_id);
+    assert_eq!(
+        env.try_add_transaction_to_pool(tx),
+        Err(InvalidTxError::ActionsValidation(
+            ActionsValidationError::NonrefundableTransferAmountZero
+        ))
+    );
+    assert_eq!(env.clients[0].sharded_tx_pool.len(ShardUId::single_shard()), 0);
+}
+
+/// VM details are collected for contracts deployed on a sponsored account.
+#[test]
+fn deploy_contract_on_sponsored_account_vm_details() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let contract = near_test_contracts::sized_contract(1500);
+    let actions =
+        sponsored_creation(&new_account_id, NEAR_BASE).deploy(contract.clone()).actions().to_vec();
+    let (tx_result, vm_details) = execute_transaction_with_vm_details(
+        &mut env,
+        actions,
+        &signer(),
+        new_account_id.clone(),
+        true,
+    );
+    tx_result.unwrap().assert_success();
+    assert!(env.query_account(new_account_id).has_contract());
+
+    assert_eq!(vm_details.len(), 1);
+    assert_eq!(vm_details[0].code_size, contract.len());
+    assert!(vm_details[0].gas_burnt > 0);
+    assert_eq!(vm_details[0].compilation_result, Ok(()));
+}
+
+/// The logs of a sponsored creation are the one the runtime emits for the
+/// sponsorship itself, followed by the ones of the contract called on the new
+/// account.
+#[test]
+fn sponsored_account_creation_logs() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let mut actions = sponsored_creation(&new_account_id, 10 * NEAR_BASE)
+        .deploy(near_test_contracts::rs_contract().to_vec())
+        .actions()
+        .to_vec();
+    actions.push(Action::FunctionCall(Box::new(FunctionCallAction {
+        method_name: "log_something".to_string(),
+        args: vec![],
+        gas: 30_000_000_000_000,
+        deposit: 0,
+    })));
+    let tx_result =
+        execute_transaction_from_actions(&mut env, actions, &signer(), new_account_id.clone())
+            .unwrap();
+    tx_result.assert_success();
+    let sponsorship_log = nonrefundable_transfer_event(&new_account_id, 10 * NEAR_BASE);
+    tx_result.assert_logs_eq(&[sponsorship_log.as_str(), "hello"]);
+}
+
+/// NEP-297 event the runtime logs for a successful non-refundable transfer.
+fn nonrefundable_transfer_event(receiver_id: &AccountId, amount: Balance) -> String {
+    let data_json = format!(r#"[{{"receiver_id":"{receiver_id}","amount":"{amount}"}}]"#);
+    let json = format!(
+        r#"{{"standard":"nep491","version":"1.0.0","event":"nonrefundable_transfer","data":{data_json}}}"#
+    );
+    format!("EVENT_JSON:{json}")
+}
+
+/// A successful non-refundable transfer is logged in the outcome of the
+/// receipt creating the account.
+#[test]
+fn non_refundable_transfer_log() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let actions = sponsored_creation_actions(&new_account_id, NEAR_BASE);
+    let tx_result =
+        execute_transaction_from_actions(&mut env, actions, &signer(), new_account_id.clone())
+            .unwrap();
+    tx_result.assert_success();
+    let receipt_outcome = &tx_result.receipts_outcome[0].outcome;
+    assert_eq!(receipt_outcome.executor_id, new_account_id);
+    assert_eq!(
+        receipt_outcome.logs,
+        vec![nonrefundable_transfer_event(&new_account_id, NEAR_BASE)]
+    );
+}
+
+/// No log is emitted when a non-refundable transfer to an existing account is
+/// rejected.
+#[test]
+fn no_non_refundable_transfer_log_on_rejection() {
+    let mut env = setup_env();
+    let actions = TransactionBuilder::new().nonrefundable_transfer(NEAR_BASE).actions().to_vec();
+    let tx_result =
+        execute_transaction_from_actions(&mut env, actions, &signer(), receiver()).unwrap();
+    assert_eq!(
+        tx_result.status,
+        FinalExecutionStatus::Failure(TxExecutionError::ActionError(ActionError {
+            index: Some(0),
+            kind: ActionErrorKind::NonRefundableBalanceToExistingAccount { account_id: receiver() },
+        }))
+    );
+    for receipt_outcome in &tx_result.receipts_outcome {
+        assert!(receipt_outcome.outcome.logs.is_empty(), "{:?}", receipt_outcome.outcome.logs);
     }
 }
 
-/// Non-refundable balance allows to have account with zero balance and more than 1kB of state.
+/// A non-refundable transfer placed before the `CreateAccount` action creating
+/// its receiver fails with an error explaining the required order.
 #[test]
-fn non_refundable_balance_allows_1kb_state_with_zero_balance() {
+fn reject_non_refundable_transfer_before_create_account() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let actions = vec![
+        Action::NonrefundableStorageTransfer(NonrefundableStorageTransferAction {
+            deposit: NEAR_BASE,
+        }),
+        Action::CreateAccount(CreateAccountAction {}),
+        Action::AddKey(Box::new(AddKeyAction {
+            public_key: PublicKey::from_seed(KeyType::ED25519, new_account_id.as_str()),
+            access_key: AccessKey { nonce: 0, permission: AccessKeyPermission::FullAccess },
+        })),
+    ];
+    let tx_result =
+        execute_transaction_from_actions(&mut env, actions, &signer(), new_account_id.clone())
+            .unwrap();
+    assert_eq!(
+        tx_result.status,
+        FinalExecutionStatus::Failure(TxExecutionError::ActionError(ActionError {
+            index: Some(0),
+            kind: ActionErrorKind::NonRefundableBalanceBeforeAccountCreation {
+                account_id: new_account_id.clone()
+            },
+        }))
+    );
+    assert!(!account_exists(&mut env, new_account_id));
+}
+
+/// Tokens burnt by a sponsored account creation match the gas burnt at the
+/// gas price of the block each outcome was applied in.
+#[test]
+fn sponsored_account_creation_tokens_burnt_at_block_gas_price() {
     let mut env = setup_env();
+    let genesis_header = env.clients[0].chain.genesis_block().header().clone();
+    let min_gas_price = genesis_header.next_gas_price();
+    assert_eq!(env.gas_price_at(genesis_header.hash()), min_gas_price);
+    let tip = env.clients[0].chain.head().unwrap();
+    for i in 1..=5 {
+        env.produce_block(0, tip.height + i);
+    }
+    // Blocks are empty, so the price stays at its minimum.
+    for height in tip.height + 1..=tip.height + 5 {
+        let block_hash = *env.clients[0].chain.get_block_header_by_height(height).unwrap().hash();
+        assert_eq!(env.gas_price_at(&block_hash), min_gas_price);
+    }
+
     let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
     let tx_result = exec_transfers(
         &mut env,
@@ -336,110 +2857,240 @@ fn non_refundable_balance_allows_1kb_state_with_zero_balance() {
         TransferConfig {
             transfers: Transfers {
                 regular_amount: 0,
-                nonrefundable_amount: NEAR_BASE / 5,
+                nonrefundable_amount: NEAR_BASE,
                 nonrefundable_transfer_first: true,
             },
             account_creation: true,
             implicit_account_creation: false,
-            deploy_contract: true,
+            deploy_contract: false,
         },
-    );
-    tx_result.unwrap().assert_success();
+    )
+    .unwrap();
+    tx_result.assert_success();
+    let outcomes =
+        std::iter::once(&tx_result.transaction_outcome).chain(&tx_result.receipts_outcome);
+    for outcome in outcomes {
+        let gas_price = env.gas_price_at(&outcome.block_hash);
+        assert_eq!(outcome.outcome.tokens_burnt, outcome.outcome.gas_burnt as Balance * gas_price);
+    }
 }
 
-/// Non-refundable transfer successfully adds non-refundable balance when creating named account.
+/// A same-shard sponsored creation with deployment resolves in four blocks:
+///
+/// 1. The chunk of the first block was produced before the transaction arrived.
+/// 2. The transaction is converted into a receipt.
+/// 3. The receipt creates the account and deploys the contract.
+/// 4. The gas refund for the inflated receipt gas price is applied.
 #[test]
-fn non_refundable_transfer_create_named_account() {
-    for (index, transfers) in TEST_CASES.iter().enumerate() {
-        let account_name = format!("subaccount{}.test0", index).to_string();
-        let new_account_id: AccountId = account_name.parse().unwrap();
-        let tx_result = exec_transfers(
-            &mut setup_env(),
-            signer(),
-            new_account_id,
-            TransferConfig {
-                transfers: transfers.clone(),
-                account_creation: true,
-                implicit_account_creation: false,
-                deploy_contract: false,
-            },
-        );
-        tx_result.unwrap().assert_success();
-    }
+fn sponsored_creation_with_deploy_resolves_in_four_blocks() {
+    let mut env = setup_env();
+    let new_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let actions = sponsored_creation(&new_account_id, NEAR_BASE)
+        .deploy(near_test_contracts::sized_contract(1500))
+        .actions()
+        .to_vec();
+    let tx = env.tx_from_actions(actions, &signer(), new_account_id);
+    let tx_hash = tx.get_hash();
+    assert_eq!(env.blocks_to_resolve(tx), 4);
+    env.clients[0].chain.get_final_transaction_result(&tx_hash).unwrap().assert_success();
 }
 
-/// Non-refundable transfer successfully adds non-refundable balance when creating NEAR-implicit account.
+/// The gas cost of a non-refundable transfer does not depend on its deposit.
 #[test]
-fn non_refundable_transfer_create_near_implicit_account() {
-    for (index, transfers) in TEST_CASES.iter().enumerate() {
-        let public_key =
-            PublicKey::from_seed(KeyType::ED25519, &format!("near{}", index).to_string());
-        let new_account_id = derive_near_implicit_account_id(public_key.unwrap_as_ed25519());
-        let tx_result = exec_transfers(
-            &mut setup_env(),
-            signer(),
+fn non_refundable_transfer_gas_independent_of_deposit() {
+    let mut env = setup_env();
+    let mut gas_burnt = |index: usize, nonrefundable_amount: Balance| {
+        let new_account_id: AccountId = format!("subaccount{index}.test0").parse().unwrap();
+        // The regular transfer covers the storage, so that both creations succeed.
+        let actions = TransactionBuilder::new()
+            .create_account()
+            .add_key(PublicKey::from_seed(KeyType::ED25519, new_account_id.as_str()))
+            .transfer(NEAR_BASE)
+            .nonrefundable_transfer(nonrefundable_amount)
+            .actions()
+            .to_vec();
+        let tx_result =
+            execute_transaction_from_actions(&mut env, actions, &signer(), new_account_id).unwrap();
+        tx_result.assert_success();
+        (
+            tx_result.transaction_outcome.outcome.gas_burnt,
+            tx_result.receipts_outcome[0].outcome.gas_burnt,
+        )
+    };
+    assert_eq!(gas_burnt(0, 1), gas_burnt(1, NEAR_BASE));
+}
+
+/// The gas burnt by a deploy receipt is the predicted deploy gas on top of
+/// the cost of the receipt.
+#[test]
+fn deploy_contract_gas_prediction() {
+    let mut env = setup_env();
+    let contract = near_test_contracts::sized_contract(1500);
+    let fee_helper = fee_helper();
+    let predicted_gas = fee_helper.deploy_contract_gas(contract.len() as u64);
+    let actions = TransactionBuilder::new().deploy(contract).actions().to_vec();
+    let tx_result =
+        execute_transaction_from_actions(&mut env, actions, &signer(), sender()).unwrap();
+    tx_result.assert_success();
+
+    let receipt_gas = tx_result.receipts_outcome[0].outcome.gas_burnt;
+    let receipt_base_gas = fee_helper.cfg().fee(ActionCosts::new_action_receipt).exec_fee();
+    assert_eq!(receipt_gas, receipt_base_gas + predicted_gas);
+}
+
+/// A sponsored account can be created and deployed to by a signer that holds
+/// exactly enough balance for the minimum gas, but not with one yoctoNEAR less.
+#[test]
+fn create_and_deploy_with_min_attached_gas() {
+    let payer_id: AccountId = "payer.test0".parse().unwrap();
+    let payer = InMemorySigner::from_seed(payer_id.clone(), KeyType::ED25519, payer_id.as_str());
+    let new_account_id: AccountId = "subaccount.payer.test0".parse().unwrap();
+    let nonrefundable_amount = NEAR_BASE / 5;
+    let actions = sponsored_creation(&new_account_id, nonrefundable_amount)
+        .deploy(near_test_contracts::sized_contract(1500))
+        .actions()
+        .to_vec();
+    let fee_helper = fee_helper();
+    assert!(fee_helper.min_attached_gas(&actions, &new_account_id) > 0);
+    let min_cost =
+        fee_helper.min_attached_gas_cost(&actions, &new_account_id) + nonrefundable_amount;
+
+    for deficit in [0, 1] {
+        let mut env = setup_env();
+        let payer_actions = TransactionBuilder::new()
+            .create_account()
+            .add_key(payer.public_key.clone())
+            .transfer(min_cost - deficit)
+            .actions()
+            .to_vec();
+        execute_transaction_from_actions(&mut env, payer_actions, &signer(), payer_id.clone())
+            .unwrap()
+            .assert_success();
+
+        let tx_result = execute_transaction_from_actions(
+            &mut env,
+            actions.clone(),
+            &payer,
             new_account_id.clone(),
-            TransferConfig {
-                transfers: transfers.clone(),
-                account_creation: true,
-                implicit_account_creation: true,
-                deploy_contract: false,
-            },
         );
-        if transfers.regular_amount == 0 {
+        if deficit == 0 {
             tx_result.unwrap().assert_success();
+            assert!(account_exists(&mut env, new_account_id.clone()));
         } else {
-            // Non-refundable transfer must be the only action in an implicit account creation transaction.
-            let status = &tx_result.unwrap().receipts_outcome[0].outcome.status;
-            assert!(matches!(
-                status,
-                ExecutionStatusView::Failure(TxExecutionError::ActionError(
-                    ActionError { kind: ActionErrorKind::AccountDoesNotExist { account_id }, .. }
-                )) if *account_id == new_account_id,
-            ));
+            assert_eq!(
+                tx_result,
+                Err(InvalidTxError::NotEnoughBalance {
+                    signer_id: payer_id.clone(),
+                    balance: min_cost - deficit,
+                    cost: min_cost,
+                    nonrefundable_balance: 0,
+                })
+            );
         }
     }
 }
 
-/// Non-refundable transfer successfully adds non-refundable balance when creating ETH-implicit account.
+/// Transactions composed by `TransactionBuilder` execute successfully, unless
+/// the builder already rejects them because of implicit account constraints.
 #[test]
-fn non_refundable_transfer_create_eth_implicit_account() {
-    for (index, transfers) in TEST_CASES.iter().enumerate() {
-        let public_key =
-            PublicKey::from_seed(KeyType::SECP256K1, &format!("eth{}", index).to_string());
-        let new_account_id = derive_eth_implicit_account_id(public_key.unwrap_as_secp256k1());
-        let tx_result = exec_transfers(
-            &mut setup_env(),
-            signer(),
-            new_account_id.clone(),
-            TransferConfig {
-                transfers: transfers.clone(),
-                account_creation: true,
-                implicit_account_creation: true,
-                deploy_contract: false,
-            },
-        );
-        if transfers.regular_amount == 0 {
-            tx_result.unwrap().assert_success();
-        } else {
-            // Non-refundable transfer must be the only action in an implicit account creation transaction.
-            let status = &tx_result.unwrap().receipts_outcome[0].outcome.status;
-            assert!(matches!(
-                status,
-                ExecutionStatusView::Failure(TxExecutionError::ActionError(
-                    ActionError { kind: ActionErrorKind::AccountDoesNotExist { account_id }, .. }
-                )) if *account_id == new_account_id,
-            ));
-        }
+fn transaction_builder_mixed_actions() {
+    let mut env = setup_env();
+    let named_account_id: AccountId = "subaccount.test0".parse().unwrap();
+    let near_implicit_account_id = derive_near_implicit_account_id(
+        PublicKey::from_seed(KeyType::ED25519, "near").unwrap_as_ed25519(),
+    );
+    let eth_implicit_account_id = derive_eth_implicit_account_id(
+        PublicKey::from_seed(KeyType::SECP256K1, "eth").unwrap_as_secp256k1(),
+    );
+
+    let valid_transactions = [
+        (
+            sponsored_creation(&named_account_id, NEAR_BASE)
+                .transfer(1)
+                .deploy(near_test_contracts::sized_contract(1500)),
+            named_account_id,
+        ),
+        (TransactionBuilder::new().nonrefundable_transfer(1), near_implicit_account_id.clone()),
+        (TransactionBuilder::new().transfer(1), eth_implicit_account_id.clone()),
+    ];
+    for (builder, receiver) in valid_transactions {
+        let tip = env.clients[0].chain.head().unwrap();
+        let nonce = get_nonce(&mut env, &signer()) + 1;
+        let tx = builder.build(&signer(), receiver.clone(), nonce, tip.last_block_hash).unwrap();
+        env.execute_tx(tx).unwrap().assert_success();
+        assert!(account_exists(&mut env, receiver));
     }
+
+    let tip = env.clients[0].chain.head().unwrap();
+    let tx_result = TransactionBuilder::new().nonrefundable_transfer(1).transfer(1).build(
+        &signer(),
+        near_implicit_account_id.clone(),
+        1,
+        tip.last_block_hash,
+    );
+    assert_eq!(
+        tx_result.unwrap_err(),
+        TransactionBuilderError::NonrefundableTransferToImplicitAccountWithOtherActions {
+            account_id: near_implicit_account_id
+        }
+    );
+    let tx_result = TransactionBuilder::new().create_account().transfer(1).build(
+        &signer(),
+        eth_implicit_account_id.clone(),
+        1,
+        tip.last_block_hash,
+    );
+    assert_eq!(
+        tx_result.unwrap_err(),
+        TransactionBuilderError::CreateImplicitAccount { account_id: eth_implicit_account_id }
+    );
+}
+
+/// Builds a transaction with `action_count` actions, all of them zero-deposit
+/// transfers, so that the number of actions is the only thing that can make
+/// it invalid.
+fn oversized_transaction(
+    env: &mut TestEnv,
+    signer: &InMemorySigner,
+    receiver: AccountId,
+    action_count: u64,
+) -> SignedTransaction {
+    let actions = (0..action_count).map(|_| Action::Transfer(TransferAction { deposit: 0 }));
+    env.tx_from_actions(actions.collect(), signer, receiver)
+}
+
+/// A transaction is accepted with as many actions as allowed per receipt, but
+/// rejected with one more.
+#[test]
+fn reject_transaction_above_action_count_limit() {
+    let mut env = setup_env();
+    let epoch_id = env.clients[0].chain.head().unwrap().epoch_id;
+    let limit =
+        env.get_runtime_config(0, epoch_id).wasm_config.limit_config.max_actions_per_receipt;
+
+    let tx = oversized_transaction(&mut env, &signer(), receiver(), limit);
+    env.execute_tx(tx).unwrap().assert_success();
+
+    let tx = oversized_transaction(&mut env, &signer(), receiver(), limit + 1);
+    assert_eq!(
+        env.execute_tx(tx),
+        Err(InvalidTxError::ActionsValidation(
+            ActionsValidationError::TotalNumberOfActionsExceeded {
+                total_number_of_actions: limit + 1,
+                limit,
+            }
+        ))
+    );
 }
 
 /// Non-refundable transfer is rejected on existing account.
 #[test]
 fn reject_non_refundable_transfer_existing_account() {
-    for transfers in TEST_CASES {
+    let mut outcomes = vec![];
+    for_each_test_case(|env, index, transfers| {
+        let receiver_before = env.query_account(receiver());
         let tx_result = exec_transfers(
-            &mut setup_env(),
+            env,
             signer(),
             receiver(),
             TransferConfig {
@@ -449,14 +3100,56 @@ fn reject_non_refundable_transfer_existing_account() {
                 deploy_contract: false,
             },
         );
-        let status = &tx_result.unwrap().receipts_outcome[0].outcome.status;
-        assert!(matches!(
-            status,
-            ExecutionStatusView::Failure(TxExecutionError::ActionError(
-                ActionError { kind: ActionErrorKind::NonRefundableBalanceToExistingAccount { account_id }, .. }
-            )) if *account_id == receiver(),
-        ));
-    }
+        let outcome = tx_result.unwrap_or_else(|err| panic!("test case {index}: {err}"));
+        // The transfer fails at execution, so the sender pays for the gas.
+        env.assert_gas_burnt_positive(&outcome);
+        outcomes.push(outcome);
+        // Neither `amount` nor `nonrefundable` of the receiver were touched.
+        env.assert_account_unchanged(receiver(), &receiver_before);
+    });
+    assert_all_failed_with(
+        &outcomes,
+        &ActionErrorKind::NonRefundableBalanceToExistingAccount { account_id: receiver() },
+    );
+}
+
+/// The reported activation height of non-refundable transfers is the first
+/// block of the first epoch in which they are supported.
+#[test]
+fn non_refundable_balance_activation_height() {
+    let feature = ProtocolFeature::NonRefundableBalance;
+    let mut genesis = Genesis::test(vec![sender(), receiver()], 1);
+    genesis.config.epoch_length = 5;
+    genesis.config.protocol_version = feature.protocol_version() - 1;
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    assert_eq!(env.clients[0].chain.feature_activation_height(feature).unwrap(), None);
+
+    env.upgrade_protocol(feature.protocol_version());
+    let chain = &env.clients[0].chain;
+    let activation_height = chain.feature_activation_height(feature).unwrap().unwrap();
+    let protocol_version_at = |height| {
+        let block_hash = chain.get_block_hash_by_height(height).unwrap();
+        let epoch_id = chain.get_block_header(&block_hash).unwrap().epoch_id().clone();
+        env.clients[0].epoch_manager.get_epoch_protocol_version(&epoch_id).unwrap()
+    };
+    assert!(protocol_version_at(activation_height) >= feature.protocol_version());
+    assert!(protocol_version_at(activation_height - 1) < feature.protocol_version());
+}
+
+/// The current protocol version is the genesis one until the upgrade to a
+/// version supporting non-refundable transfers was voted for.
+#[test]
+fn current_protocol_version_follows_upgrade() {
+    let old_protocol_version = ProtocolFeature::NonRefundableBalance.protocol_version() - 1;
+    let mut env = setup_env_with_protocol_version(Some(old_protocol_version));
+    assert_eq!(env.current_protocol_version(), old_protocol_version);
+
+    env.upgrade_protocol(ProtocolFeature::NonRefundableBalance.protocol_version());
+    // Blocks produced after the upgrade vote for the latest version, which
+    // may be newer than the one supporting the feature.
+    assert!(
+        env.current_protocol_version() >= ProtocolFeature::NonRefundableBalance.protocol_version()
+    );
 }
 
 /// During the protocol upgrade phase, before the voting completes, we must not
@@ -467,12 +3160,15 @@ fn reject_non_refundable_transfer_existing_account() {
 /// returned for older protocol versions.
 #[test]
 fn reject_non_refundable_transfer_in_older_versions() {
-    let mut env = setup_env_with_protocol_version(Some(
-        ProtocolFeature::NonRefundableBalance.protocol_version() - 1,
-    ));
-    for transfers in TEST_CASES {
+    let setup = || {
+        setup_env_with_protocol_version(Some(
+            ProtocolFeature::NonRefundableBalance.protocol_version() - 1,
+        ))
+    };
+    for_each_test_case_with_env(setup, |env, index, transfers| {
+        let sender_before = env.query_account(sender());
         let tx_result = exec_transfers(
-            &mut env,
+            env,
             signer(),
             receiver(),
             TransferConfig {
@@ -483,13 +3179,36 @@ fn reject_non_refundable_transfer_in_older_versions() {
             },
         );
         assert_eq!(
-            tx_result,
-            Err(InvalidTxError::ActionsValidation(
-                ActionsValidationError::UnsupportedProtocolFeature {
-                    protocol_feature: "NonRefundableBalance".to_string(),
-                    version: ProtocolFeature::NonRefundableBalance.protocol_version()
-                }
-            ))
+            tx_result.as_ref().unwrap_err().gating_feature(),
+            Some(ProtocolFeature::NonRefundableBalance),
+            "test case {index}"
         );
+        env.assert_no_gas_burnt_on_rejection(&tx_result, sender(), &sender_before);
+    });
+}
+
+/// A non-refundable transfer is rejected before entering the pool on a
+/// protocol version before the activation of the feature, and accepted into
+/// the pool once the feature is active.
+#[test]
+fn non_refundable_transfer_pool_admission_across_versions() {
+    let activation_version = ProtocolFeature::NonRefundableBalance.protocol_version();
+    for protocol_version in [activation_version - 1, activation_version] {
+        let mut env = setup_env_with_protocol_version(Some(protocol_version));
+        let actions =
+            TransactionBuilder::new().nonrefundable_transfer(NEAR_BASE).actions().to_vec();
+        let tx = env.tx_from_actions(actions, &signer(), receiver());
+        let result = env.try_add_transaction_to_pool(tx);
+        let pool_len = env.clients[0].sharded_tx_pool.len(ShardUId::single_shard());
+        if protocol_version < activation_version {
+            assert_eq!(
+                result.unwrap_err().gating_feature(),
+                Some(ProtocolFeature::NonRefundableBalance)
+            );
+            assert_eq!(pool_len, 0);
+        } else {
+            result.unwrap();
+            assert_eq!(pool_len, 1);
+        }
     }
 }
@@ -393,15 +393,22 @@ fn test_tx_not_enough_balance_must_return_error() {
             let _ = client
                 .broadcast_tx_commit(to_base64(&bytes))
                 .map_err(|err| {
+                    let not_enough_balance = serde_json::json!({
+                        "signer_id": "near.0",
+                        "balance": "950000000000000000000000000000000", // If something changes in setup just update this value
+                        "cost": "1100000000000045306060187500000000",
+                    });
+                    #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+                    let not_enough_balance = {
+                        let mut not_enough_balance = not_enough_balance;
+                        not_enough_balance["nonrefundable_balance"] = "0".into();
+                        not_enough_balance
+                    };
                     assert_eq!(
                         err.data.unwrap(),
                         serde_json::json!({"TxExecutionError": {
                             "InvalidTxError": {
-                                "NotEnoughBalance": {
-                                    "signer_id": "near.0",
-                                    "balance": "950000000000000000000000000000000", // If something changes in setup just update this value
-                                    "cost": "1100000000000045306060187500000000",
-                                }
+                                "NotEnoughBalance": not_enough_balance
                             }
                         }})
                     );
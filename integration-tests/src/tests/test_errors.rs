@@ -97,7 +97,9 @@ fn test_deliver_tx_error_log() {
         InvalidTxError::NotEnoughBalance {
             signer_id: alice_account(),
             balance: TESTING_INIT_BALANCE - TESTING_INIT_STAKE,
-            cost: TESTING_INIT_BALANCE + 1 + cost
+            cost: TESTING_INIT_BALANCE + 1 + cost,
+            #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+            nonrefundable_balance: 0,
         }
         .rpc_into()
     );
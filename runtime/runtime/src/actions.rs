@@ -15,6 +15,8 @@ use near_primitives::config::ViewConfig;
 use near_primitives::errors::{ActionError, ActionErrorKind, InvalidAccessKeyError, RuntimeError};
 use near_primitives::hash::CryptoHash;
 use near_primitives::receipt::{ActionReceipt, DataReceipt, Receipt, ReceiptEnum};
+#[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+use near_primitives::transaction::DeleteAccountActionV2;
 use near_primitives::transaction::{
     Action, AddKeyAction, DeleteAccountAction, DeleteKeyAction, DeployContractAction,
     FunctionCallAction, StakeAction,
@@ -679,6 +681,40 @@ pub(crate) fn action_delete_account(
     Ok(())
 }
 
+/// Deletes the account like `action_delete_account`, but fails instead of
+/// burning its non-refundable balance unless the action allows it.
+#[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+pub(crate) fn action_delete_account_v2(
+    state_update: &mut TrieUpdate,
+    account: &mut Option<Account>,
+    actor_id: &mut AccountId,
+    receipt: &Receipt,
+    result: &mut ActionResult,
+    account_id: &AccountId,
+    delete_account: &DeleteAccountActionV2,
+    current_protocol_version: ProtocolVersion,
+) -> Result<(), StorageError> {
+    let nonrefundable = account.as_ref().unwrap().nonrefundable();
+    if !delete_account.allow_nonrefundable_burn && nonrefundable > 0 {
+        result.result = Err(ActionErrorKind::CannotDeleteAccountWithNonrefundableBalance {
+            account_id: account_id.clone(),
+            nonrefundable,
+        }
+        .into());
+        return Ok(());
+    }
+    action_delete_account(
+        state_update,
+        account,
+        actor_id,
+        receipt,
+        result,
+        account_id,
+        &DeleteAccountAction { beneficiary_id: delete_account.beneficiary_id.clone() },
+        current_protocol_version,
+    )
+}
+
 pub(crate) fn action_delete_key(
     fee_config: &RuntimeFeesConfig,
     state_update: &mut TrieUpdate,
@@ -1003,6 +1039,24 @@ pub(crate) fn check_actor_permissions(
                 .into());
             }
         }
+        // TODO(nonrefundable) Merge with arm above on stabilization.
+        #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+        Action::DeleteAccountV2(_) => {
+            if actor_id != account_id {
+                return Err(ActionErrorKind::ActorNoPermission {
+                    account_id: account_id.clone(),
+                    actor_id: actor_id.clone(),
+                }
+                .into());
+            }
+            let account = account.as_ref().unwrap();
+            if account.locked() != 0 {
+                return Err(ActionErrorKind::DeleteAccountStaking {
+                    account_id: account_id.clone(),
+                }
+                .into());
+            }
+        }
         Action::CreateAccount(_) | Action::FunctionCall(_) | Action::Transfer(_) => (),
         Action::Delegate(_) => (),
         #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
@@ -1022,6 +1076,11 @@ pub(crate) fn check_account_existence(
         allow(unused_variables)
     )]
     receipt_starts_with_create_account: bool,
+    #[cfg_attr(
+        not(feature = "protocol_feature_nonrefundable_transfer_nep491"),
+        allow(unused_variables)
+    )]
+    receipt_creates_account_later: bool,
 ) -> Result<(), ActionError> {
     match action {
         Action::CreateAccount(_) => {
@@ -1063,7 +1122,14 @@ pub(crate) fn check_account_existence(
         // TODO(nonrefundable) Merge with arm above on stabilization.
         #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
         Action::NonrefundableStorageTransfer(_) => {
-            if account.is_none() {
+            if account.is_none() && receipt_creates_account_later {
+                // The account would only be created by a later action, so the
+                // non-refundable transfer is in the wrong place.
+                return Err(ActionErrorKind::NonRefundableBalanceBeforeAccountCreation {
+                    account_id: account_id.clone(),
+                }
+                .into());
+            } else if account.is_none() {
                 return check_transfer_to_nonexisting_account(
                     config,
                     account_id,
@@ -1098,10 +1164,98 @@ pub(crate) fn check_account_existence(
                 .into());
             }
         }
+        // TODO(nonrefundable) Merge with arm above on stabilization.
+        #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+        Action::DeleteAccountV2(_) => {
+            if account.is_none() {
+                return Err(ActionErrorKind::AccountDoesNotExist {
+                    account_id: account_id.clone(),
+                }
+                .into());
+            }
+        }
     };
     Ok(())
 }
 
+/// Error kinds that `check_actor_permissions` and `check_account_existence`
+/// could return for the action, without knowing the state of the accounts or
+/// the other actions of the receipt.
+///
+/// Errors that depend on the execution itself, such as function call errors
+/// or lack of balance, are not listed.
+pub fn possible_action_error_kinds(
+    action: &Action,
+    actor_id: &AccountId,
+    account_id: &AccountId,
+) -> Vec<ActionErrorKind> {
+    let mut kinds = vec![];
+    match action {
+        Action::DeployContract(_)
+        | Action::Stake(_)
+        | Action::AddKey(_)
+        | Action::DeleteKey(_)
+        | Action::DeleteAccount(_) => {
+            if actor_id != account_id {
+                kinds.push(ActionErrorKind::ActorNoPermission {
+                    account_id: account_id.clone(),
+                    actor_id: actor_id.clone(),
+                });
+            }
+        }
+        #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+        Action::DeleteAccountV2(_) => {
+            if actor_id != account_id {
+                kinds.push(ActionErrorKind::ActorNoPermission {
+                    account_id: account_id.clone(),
+                    actor_id: actor_id.clone(),
+                });
+            }
+        }
+        Action::CreateAccount(_) | Action::FunctionCall(_) | Action::Transfer(_) => (),
+        Action::Delegate(_) => (),
+        #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+        Action::NonrefundableStorageTransfer(_) => (),
+    }
+
+    let account_id = account_id.clone();
+    match action {
+        Action::CreateAccount(_) => {
+            kinds.push(ActionErrorKind::AccountAlreadyExists { account_id: account_id.clone() });
+            if account_id.get_account_type().is_implicit() {
+                kinds.push(ActionErrorKind::OnlyImplicitAccountCreationAllowed { account_id });
+            }
+        }
+        #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+        Action::NonrefundableStorageTransfer(_) => {
+            kinds.push(ActionErrorKind::AccountDoesNotExist { account_id: account_id.clone() });
+            kinds.push(ActionErrorKind::NonRefundableBalanceToExistingAccount {
+                account_id: account_id.clone(),
+            });
+            kinds.push(ActionErrorKind::NonRefundableBalanceBeforeAccountCreation { account_id });
+        }
+        Action::DeleteAccount(_) => {
+            kinds.push(ActionErrorKind::AccountDoesNotExist { account_id: account_id.clone() });
+            kinds.push(ActionErrorKind::DeleteAccountStaking { account_id });
+        }
+        #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+        Action::DeleteAccountV2(_) => {
+            kinds.push(ActionErrorKind::AccountDoesNotExist { account_id: account_id.clone() });
+            kinds.push(ActionErrorKind::DeleteAccountStaking { account_id });
+        }
+        Action::DeployContract(_)
+        | Action::FunctionCall(_)
+        | Action::Transfer(_)
+        | Action::Stake(_)
+        | Action::AddKey(_)
+        | Action::DeleteKey(_)
+        | Action::Delegate(_) => {
+            kinds.push(ActionErrorKind::AccountDoesNotExist { account_id });
+        }
+    }
+    kinds
+}
+
 fn check_transfer_to_nonexisting_account(
     config: &RuntimeConfig,
     account_id: &AccountId,
@@ -1135,7 +1289,9 @@ mod tests {
     use near_primitives::errors::InvalidAccessKeyError;
     use near_primitives::hash::hash;
     use near_primitives::runtime::migration_data::MigrationFlags;
-    use near_primitives::transaction::CreateAccountAction;
+    #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+    use near_primitives::test_utils::MockEpochInfoProvider;
+    use near_primitives::transaction::{CreateAccountAction, TransferAction};
     use near_primitives::trie_key::TrieKey;
     use near_primitives::types::{EpochId, StateChangeCause};
     use near_primitives_core::version::PROTOCOL_VERSION;
@@ -1323,6 +1479,57 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+    fn test_stake_with_nonrefundable_balance(stake: Balance) -> (Account, ActionResult) {
+        let mut account = Account::new(
+            100,
+            0,
+            1000,
+            CryptoHash::default(),
+            0,
+            ProtocolFeature::NonRefundableBalance.protocol_version(),
+        );
+        let mut action_result = ActionResult::default();
+        let res = action_stake(
+            &mut account,
+            &mut action_result,
+            &"alice".parse().unwrap(),
+            &StakeAction { stake, public_key: PublicKey::empty(near_crypto::KeyType::ED25519) },
+            &CryptoHash::default(),
+            &MockEpochInfoProvider::default(),
+        );
+        assert!(res.is_ok());
+        (account, action_result)
+    }
+
+    /// Only the refundable balance can be staked, non-refundable balance stays untouched.
+    #[test]
+    #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+    fn test_stake_excludes_nonrefundable_balance() {
+        let (account, action_result) = test_stake_with_nonrefundable_balance(100);
+        assert!(action_result.result.is_ok());
+        assert_eq!(account.amount(), 0);
+        assert_eq!(account.locked(), 100);
+        assert_eq!(account.nonrefundable(), 1000);
+
+        let (account, action_result) = test_stake_with_nonrefundable_balance(101);
+        assert_eq!(
+            action_result.result,
+            Err(ActionError {
+                index: None,
+                kind: ActionErrorKind::TriesToStake {
+                    account_id: "alice".parse().unwrap(),
+                    stake: 101,
+                    locked: 0,
+                    balance: 100,
+                }
+            })
+        );
+        assert_eq!(account.amount(), 100);
+        assert_eq!(account.locked(), 0);
+        assert_eq!(account.nonrefundable(), 1000);
+    }
+
     fn create_delegate_action_receipt() -> (ActionReceipt, SignedDelegateAction) {
         let signed_delegate_action = SignedDelegateAction {
             delegate_action: DelegateAction {
@@ -1540,6 +1747,7 @@ mod tests {
                 &RuntimeConfig::test(),
                 false,
                 false,
+                false,
             ),
             Err(ActionErrorKind::AccountDoesNotExist { account_id: sender_id.clone() }.into())
         );
@@ -1906,4 +2114,147 @@ mod tests {
             .into())
         );
     }
+
+    #[test]
+    fn test_possible_action_error_kinds_add_key_by_other_actor() {
+        let actor_id: AccountId = "alice".parse().unwrap();
+        let account_id: AccountId = "bob".parse().unwrap();
+        let action = Action::AddKey(Box::new(AddKeyAction {
+            public_key: PublicKey::empty(near_crypto::KeyType::ED25519),
+            access_key: AccessKey::full_access(),
+        }));
+        assert_eq!(
+            possible_action_error_kinds(&action, &actor_id, &account_id),
+            vec![
+                ActionErrorKind::ActorNoPermission {
+                    account_id: account_id.clone(),
+                    actor_id: actor_id.clone(),
+                },
+                ActionErrorKind::AccountDoesNotExist { account_id: account_id.clone() },
+            ]
+        );
+        assert_eq!(
+            possible_action_error_kinds(&action, &account_id, &account_id),
+            vec![ActionErrorKind::AccountDoesNotExist { account_id }]
+        );
+    }
+
+    #[test]
+    fn test_possible_action_error_kinds_covers_checks() {
+        let public_key = PublicKey::empty(near_crypto::KeyType::ED25519);
+        let beneficiary_id: AccountId = "alice".parse().unwrap();
+        let (_, signed_delegate_action) = create_delegate_action_receipt();
+        let actions = vec![
+            Action::CreateAccount(CreateAccountAction {}),
+            Action::DeployContract(DeployContractAction { code: vec![] }),
+            Action::FunctionCall(Box::new(FunctionCallAction {
+                method_name: "main".to_string(),
+                args: vec![],
+                gas: 1,
+                deposit: 0,
+            })),
+            Action::Transfer(TransferAction { deposit: 1 }),
+            Action::Stake(Box::new(StakeAction { stake: 1, public_key: public_key.clone() })),
+            Action::AddKey(Box::new(AddKeyAction {
+                public_key: public_key.clone(),
+                access_key: AccessKey::full_access(),
+            })),
+            Action::DeleteKey(Box::new(DeleteKeyAction { public_key })),
+            Action::DeleteAccount(DeleteAccountAction { beneficiary_id: beneficiary_id.clone() }),
+            Action::Delegate(Box::new(signed_delegate_action)),
+            #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+            Action::DeleteAccountV2(Box::new(DeleteAccountActionV2 {
+                beneficiary_id,
+                allow_nonrefundable_burn: false,
+            })),
+            #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+            Action::NonrefundableStorageTransfer(
+                near_primitives::transaction::NonrefundableStorageTransferAction { deposit: 1 },
+            ),
+        ];
+        // Fails to compile when a new action is added, as a reminder to add
+        // it to the list above.
+        for action in &actions {
+            match action {
+                Action::CreateAccount(_)
+                | Action::DeployContract(_)
+                | Action::FunctionCall(_)
+                | Action::Transfer(_)
+                | Action::Stake(_)
+                | Action::AddKey(_)
+                | Action::DeleteKey(_)
+                | Action::DeleteAccount(_)
+                | Action::Delegate(_) => (),
+                #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+                Action::DeleteAccountV2(_) | Action::NonrefundableStorageTransfer(_) => (),
+            }
+        }
+
+        let account_ids: Vec<AccountId> = vec![
+            "bob.alice".parse().unwrap(),
+            "0f5e1a8e53bd2f1ab1b0d0d1e3f4b2a7c9d8e6f5a4b3c2d1e0f9a8b7c6d5e4f3".parse().unwrap(),
+            "0x32400084c286cf3e17e7b677ea9583e60a000324".parse().unwrap(),
+        ];
+        let accounts = [
+            None,
+            Some(Account::new(100, 0, 0, CryptoHash::default(), 100, PROTOCOL_VERSION)),
+            Some(Account::new(100, 10, 0, CryptoHash::default(), 100, PROTOCOL_VERSION)),
+        ];
+        let config = RuntimeConfig::test();
+        for action in &actions {
+            for account_id in &account_ids {
+                for actor_id in [account_id.clone(), "alice".parse().unwrap()] {
+                    let possible = possible_action_error_kinds(action, &actor_id, account_id);
+                    for account in &accounts {
+                        for flags in 0..8u8 {
+                            // Same order as in `Runtime::apply_action`.
+                            let result = check_account_existence(
+                                action,
+                                account,
+                                account_id,
+                                &config,
+                                flags & 1 != 0,
+                                flags & 2 != 0,
+                                flags & 4 != 0,
+                            )
+                            .and_then(|()| {
+                                check_actor_permissions(action, account, &actor_id, account_id)
+                            });
+                            if let Err(err) = result {
+                                assert!(
+                                    possible.contains(&err.kind),
+                                    "{:?} by {} on {}: {:?} is not in {:?}",
+                                    action,
+                                    actor_id,
+                                    account_id,
+                                    err.kind,
+                                    possible
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+    fn test_possible_action_error_kinds_nonrefundable_transfer() {
+        let actor_id: AccountId = "alice".parse().unwrap();
+        let account_id: AccountId = "bob.alice".parse().unwrap();
+        let action = Action::NonrefundableStorageTransfer(
+            near_primitives::transaction::NonrefundableStorageTransferAction { deposit: 1 },
+        );
+        assert_eq!(
+            possible_action_error_kinds(&action, &actor_id, &account_id),
+            vec![
+                ActionErrorKind::AccountDoesNotExist { account_id: account_id.clone() },
+                ActionErrorKind::NonRefundableBalanceToExistingAccount {
+                    account_id: account_id.clone()
+                },
+                ActionErrorKind::NonRefundableBalanceBeforeAccountCreation { account_id },
+            ]
+        );
+    }
 }
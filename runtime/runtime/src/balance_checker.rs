@@ -34,8 +34,9 @@ fn get_delayed_receipts(
         .collect()
 }
 
-/// Calculates and returns cost of a receipt.
-fn receipt_cost(
+/// Calculates and returns cost of a receipt, i.e. the balance it carries
+/// until it is applied.
+pub fn receipt_cost(
     config: &RuntimeConfig,
     receipt: &Receipt,
 ) -> Result<Balance, IntegerOverflowError> {
@@ -455,6 +456,7 @@ mod tests {
                 tx_burnt_amount: total_validator_reward,
                 gas_deficit_amount: 0,
                 other_burnt_amount: 0,
+                nonrefundable_burnt_amount: 0,
                 slashed_burnt_amount: 0,
             },
         )
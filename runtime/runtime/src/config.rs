@@ -136,6 +136,10 @@ pub fn total_send_fees(
             },
             DeleteKey(_) => fees.fee(ActionCosts::delete_key).send_fee(sender_is_receiver),
             DeleteAccount(_) => fees.fee(ActionCosts::delete_account).send_fee(sender_is_receiver),
+            #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+            DeleteAccountV2(_) => {
+                fees.fee(ActionCosts::delete_account).send_fee(sender_is_receiver)
+            }
             Delegate(signed_delegate_action) => {
                 let delegate_cost = fees.fee(ActionCosts::delegate).send_fee(sender_is_receiver);
                 let delegate_action = &signed_delegate_action.delegate_action;
@@ -238,6 +242,8 @@ pub fn exec_fee(config: &RuntimeConfig, action: &Action, receiver_id: &AccountId
         },
         DeleteKey(_) => fees.fee(ActionCosts::delete_key).exec_fee(),
         DeleteAccount(_) => fees.fee(ActionCosts::delete_account).exec_fee(),
+        #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+        DeleteAccountV2(_) => fees.fee(ActionCosts::delete_account).exec_fee(),
         Delegate(_) => fees.fee(ActionCosts::delegate).exec_fee(),
     }
 }
@@ -363,6 +369,20 @@ pub fn total_prepaid_gas(actions: &[Action]) -> Result<Gas, IntegerOverflowError
     Ok(total_gas)
 }
 
+/// Get the number of whole bytes of storage that the given deposit covers, e.g.
+/// the deposit of a non-refundable storage transfer.
+pub fn storage_bytes_covered(deposit: Balance, config: &RuntimeConfig) -> u64 {
+    deposit
+        .checked_div(config.storage_amount_per_byte())
+        .map_or(u64::MAX, |bytes| u64::try_from(bytes).unwrap_or(u64::MAX))
+}
+
+/// Get the deposit needed to cover the given number of bytes of storage, the
+/// inverse of `storage_bytes_covered`.
+pub fn deposit_for_storage_bytes(bytes: u64, config: &RuntimeConfig) -> Balance {
+    Balance::from(bytes) * config.storage_amount_per_byte()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -375,4 +395,30 @@ mod tests {
         assert_eq!(safe_gas_price_inflated(10000, Rational32::new(101, 100), 3).unwrap(), 10304);
         assert_eq!(safe_gas_price_inflated(10000, Rational32::new(101, 100), 32).unwrap(), 13750);
     }
+
+    #[test]
+    fn test_storage_bytes_covered() {
+        let config = RuntimeConfig::test();
+        let per_byte = config.storage_amount_per_byte();
+        assert_eq!(storage_bytes_covered(0, &config), 0);
+        assert_eq!(storage_bytes_covered(per_byte - 1, &config), 0);
+        assert_eq!(storage_bytes_covered(1500 * per_byte, &config), 1500);
+        // Rounded down
+        assert_eq!(storage_bytes_covered(1500 * per_byte - 1, &config), 1499);
+        assert_eq!(storage_bytes_covered(1500 * per_byte + 1, &config), 1500);
+    }
+
+    #[test]
+    fn test_deposit_for_storage_bytes() {
+        let config = RuntimeConfig::test();
+        let per_byte = config.storage_amount_per_byte();
+        assert_eq!(deposit_for_storage_bytes(0, &config), 0);
+        assert_eq!(deposit_for_storage_bytes(1500, &config), 1500 * per_byte);
+        assert_eq!(storage_bytes_covered(deposit_for_storage_bytes(1500, &config), &config), 1500);
+        // Covering the bytes of a non-exact deposit never needs more than it.
+        let deposit = 1500 * per_byte + per_byte / 2;
+        assert!(
+            deposit_for_storage_bytes(storage_bytes_covered(deposit, &config), &config) <= deposit
+        );
+    }
 }
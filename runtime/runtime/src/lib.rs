@@ -1,13 +1,16 @@
+pub use crate::actions::possible_action_error_kinds;
 use crate::actions::*;
 use crate::balance_checker::check_balance;
+pub use crate::balance_checker::receipt_cost;
 use crate::config::{
     exec_fee, safe_add_balance, safe_add_compute, safe_add_gas, safe_gas_to_balance, total_deposit,
     total_prepaid_exec_fees, total_prepaid_gas,
 };
 use crate::prefetch::TriePrefetcher;
-use crate::verifier::{check_storage_stake, validate_receipt, StorageStakingError};
+use crate::verifier::validate_receipt;
 pub use crate::verifier::{
-    validate_transaction, verify_and_charge_transaction, ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT,
+    check_storage_stake, ensure_protocol_feature_enabled, validate_transaction,
+    verify_and_charge_transaction, StorageStakingError, ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT,
 };
 use config::total_prepaid_send_fees;
 pub use near_crypto;
@@ -140,6 +143,9 @@ pub struct ApplyStats {
     pub tx_burnt_amount: Balance,
     pub slashed_burnt_amount: Balance,
     pub other_burnt_amount: Balance,
+    /// Non-refundable balance burnt on account deletion. It is already part of
+    /// `other_burnt_amount`, it is only reported separately for accounting.
+    pub nonrefundable_burnt_amount: Balance,
     /// This is a negative amount. This amount was not charged from the account that issued
     /// the transaction. It's likely due to the delayed queue of the receipts.
     pub gas_deficit_amount: Balance,
@@ -353,6 +359,9 @@ impl Runtime {
 
         let receipt_starts_with_create_account =
             matches!(actions.get(0), Some(Action::CreateAccount(_)));
+        let receipt_creates_account_later = actions[action_index + 1..]
+            .iter()
+            .any(|action| matches!(action, Action::CreateAccount(_)));
         // Account validation
         if let Err(e) = check_account_existence(
             action,
@@ -361,6 +370,7 @@ impl Runtime {
             &apply_state.config,
             implicit_account_creation_eligible,
             receipt_starts_with_create_account,
+            receipt_creates_account_later,
         ) {
             result.result = Err(e);
             return Ok(result);
@@ -438,6 +448,11 @@ impl Runtime {
                     apply_state,
                     actor_id,
                 )?;
+                if apply_state.current_protocol_version
+                    >= ProtocolFeature::NonRefundableBalance.protocol_version()
+                {
+                    result.logs.push(nonrefundable_transfer_event(account_id, *deposit));
+                }
             }
             Action::Stake(stake) => {
                 action_stake(
@@ -482,6 +497,19 @@ impl Runtime {
                     apply_state.current_protocol_version,
                 )?;
             }
+            #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+            Action::DeleteAccountV2(delete_account) => {
+                action_delete_account_v2(
+                    state_update,
+                    account,
+                    actor_id,
+                    receipt,
+                    &mut result,
+                    account_id,
+                    delete_account,
+                    apply_state.current_protocol_version,
+                )?;
+            }
             Action::Delegate(signed_delegate_action) => {
                 apply_delegate_action(
                     state_update,
@@ -597,14 +625,19 @@ impl Runtime {
 
             // We update `other_burnt_amount` statistic with the non-refundable amount being burnt on account deletion.
             #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
-            if matches!(action, Action::DeleteAccount(DeleteAccountAction { beneficiary_id: _ })) {
+            if matches!(
+                action,
+                Action::DeleteAccount(DeleteAccountAction { beneficiary_id: _ })
+                    | Action::DeleteAccountV2(_)
+            ) {
                 // The `account_before_update` can be None if the account is both created and deleted within
                 // a single action receipt (see `test_create_account_add_key_call_delete_key_delete_account`).
                 if let Some(ref account_before_update) = account_before_update {
-                    stats.other_burnt_amount = safe_add_balance(
-                        stats.other_burnt_amount,
-                        account_before_update.nonrefundable(),
-                    )?
+                    let nonrefundable = account_before_update.nonrefundable();
+                    stats.other_burnt_amount =
+                        safe_add_balance(stats.other_burnt_amount, nonrefundable)?;
+                    stats.nonrefundable_burnt_amount =
+                        safe_add_balance(stats.nonrefundable_burnt_amount, nonrefundable)?;
                 }
             }
         }
@@ -1704,6 +1737,33 @@ impl Runtime {
     }
 }
 
+/// NEP-297 event logged when a non-refundable transfer credits `receiver_id`,
+/// so that indexers can tell sponsored storage apart from regular transfers.
+#[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+fn nonrefundable_transfer_event(receiver_id: &AccountId, deposit: Balance) -> String {
+    #[derive(serde::Serialize)]
+    struct Event<'a> {
+        standard: &'static str,
+        version: &'static str,
+        event: &'static str,
+        data: [EventData<'a>; 1],
+    }
+
+    #[derive(serde::Serialize)]
+    struct EventData<'a> {
+        receiver_id: &'a AccountId,
+        amount: String,
+    }
+
+    let event = Event {
+        standard: "nep491",
+        version: "1.0.0",
+        event: "nonrefundable_transfer",
+        data: [EventData { receiver_id, amount: deposit.to_string() }],
+    };
+    format!("EVENT_JSON:{}", serde_json::to_string(&event).unwrap())
+}
+
 fn action_transfer_or_implicit_account_creation(
     account: &mut Option<Account>,
     deposit: u128,
@@ -2569,6 +2629,44 @@ mod tests {
         assert_eq!(result.stats.tx_burnt_amount, total_receipt_cost);
     }
 
+    /// Deleting an account burns its non-refundable balance, which is reported
+    /// on its own in the apply stats.
+    #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+    #[test]
+    fn test_delete_account_nonrefundable_burnt_amount() {
+        for nonrefundable in [0, to_yocto(1)] {
+            let (runtime, tries, root, apply_state, signer, epoch_info_provider) =
+                setup_runtime(to_yocto(1_000_000), 0, 10u64.pow(15));
+
+            let mut state_update = tries.new_trie_update(ShardUId::single_shard(), root);
+            let mut account = get_account(&state_update, &alice_account()).unwrap().unwrap();
+            account.set_nonrefundable(nonrefundable);
+            set_account(&mut state_update, alice_account(), &account);
+            state_update.commit(StateChangeCause::InitialState);
+            let trie_changes = state_update.finalize().unwrap().1;
+            let mut store_update = tries.store_update();
+            let root = tries.apply_all(&trie_changes, ShardUId::single_shard(), &mut store_update);
+            store_update.commit().unwrap();
+
+            let actions =
+                vec![Action::DeleteAccount(DeleteAccountAction { beneficiary_id: bob_account() })];
+            let receipts = vec![create_receipt_with_actions(alice_account(), signer, actions)];
+            let apply_result = runtime
+                .apply(
+                    tries.get_trie_for_shard(ShardUId::single_shard(), root),
+                    &None,
+                    &apply_state,
+                    &receipts,
+                    &[],
+                    &epoch_info_provider,
+                    Default::default(),
+                )
+                .unwrap();
+            assert_eq!(apply_result.stats.nonrefundable_burnt_amount, nonrefundable);
+            assert_eq!(apply_result.stats.other_burnt_amount, nonrefundable);
+        }
+    }
+
     #[test]
     fn test_delete_key_add_key() {
         let initial_locked = to_yocto(500_000);
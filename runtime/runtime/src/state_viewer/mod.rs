@@ -6,16 +6,19 @@ use near_crypto::{KeyType, PublicKey};
 use near_parameters::RuntimeConfigStore;
 use near_primitives::account::{AccessKey, Account};
 use near_primitives::borsh::BorshDeserialize;
+use near_primitives::errors::StorageError;
 use near_primitives::hash::CryptoHash;
-use near_primitives::receipt::ActionReceipt;
+use near_primitives::receipt::{ActionReceipt, Receipt, ReceiptEnum};
 use near_primitives::runtime::migration_data::{MigrationData, MigrationFlags};
-use near_primitives::transaction::FunctionCallAction;
-use near_primitives::trie_key::trie_key_parsers;
+use near_primitives::transaction::{Action, FunctionCallAction};
+use near_primitives::trie_key::{trie_key_parsers, TrieKey};
 use near_primitives::types::{AccountId, EpochInfoProvider, Gas};
 use near_primitives::version::PROTOCOL_VERSION;
 use near_primitives::views::{StateItem, ViewApplyState, ViewStateResult};
 use near_primitives_core::config::ViewConfig;
-use near_store::{get_access_key, get_account, get_code, TrieUpdate};
+use near_store::{
+    get, get_access_key, get_account, get_code, get_delayed_receipt_indices, TrieUpdate,
+};
 use near_vm_runner::logic::ReturnData;
 use near_vm_runner::ContractCode;
 use std::{str, sync::Arc, time::Instant};
@@ -112,6 +115,44 @@ impl TrieViewer {
         access_keys
     }
 
+    /// Returns the actions of all delayed receipts targeting `account_id`, in
+    /// queue order, together with the id of the receipt they belong to.
+    ///
+    /// The account doesn't need to exist, e.g. the receipt creating it may
+    /// still be delayed.
+    pub fn view_pending_actions(
+        &self,
+        state_update: &TrieUpdate,
+        account_id: &AccountId,
+    ) -> Result<Vec<(CryptoHash, Action)>, StorageError> {
+        let indices = get_delayed_receipt_indices(state_update)?;
+        let mut pending_actions = vec![];
+        for index in indices.first_index..indices.next_available_index {
+            let receipt: Receipt = get(state_update, &TrieKey::DelayedReceipt { index })?
+                .ok_or_else(|| {
+                    StorageError::StorageInconsistentState(format!(
+                        "Delayed receipt #{} should be in the state",
+                        index
+                    ))
+                })?;
+            if &receipt.receiver_id != account_id {
+                continue;
+            }
+            match receipt.receipt {
+                ReceiptEnum::Action(action_receipt) | ReceiptEnum::PromiseYield(action_receipt) => {
+                    pending_actions.extend(
+                        action_receipt
+                            .actions
+                            .into_iter()
+                            .map(|action| (receipt.receipt_id, action)),
+                    );
+                }
+                ReceiptEnum::Data(_) | ReceiptEnum::PromiseResume(_) => {}
+            }
+        }
+        Ok(pending_actions)
+    }
+
     pub fn view_state(
         &self,
         state_update: &TrieUpdate,
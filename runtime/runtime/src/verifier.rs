@@ -12,6 +12,8 @@ use near_primitives::errors::{
 };
 use near_primitives::receipt::{ActionReceipt, DataReceipt, Receipt, ReceiptEnum};
 use near_primitives::transaction::DeleteAccountAction;
+#[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+use near_primitives::transaction::NonrefundableStorageTransferAction;
 use near_primitives::transaction::{
     Action, AddKeyAction, DeployContractAction, FunctionCallAction, SignedTransaction, StakeAction,
 };
@@ -119,6 +121,10 @@ pub fn validate_transaction(
     )
     .map_err(InvalidTxError::ActionsValidation)?;
 
+    #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+    validate_nonrefundable_transfer_receiver(&transaction.receiver_id, &transaction.actions)
+        .map_err(InvalidTxError::ActionsValidation)?;
+
     let sender_is_receiver = &transaction.receiver_id == signer_id;
 
     tx_cost(&config, transaction, gas_price, sender_is_receiver)
@@ -194,6 +200,8 @@ pub fn verify_and_charge_transaction(
             signer_id: signer_id.clone(),
             balance: signer.amount(),
             cost: total_cost,
+            #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+            nonrefundable_balance: signer.nonrefundable(),
         }
     })?);
 
@@ -357,18 +365,20 @@ pub(crate) fn validate_actions(
     let mut found_delegate_action = false;
     let mut iter = actions.iter().peekable();
     while let Some(action) = iter.next() {
-        if let Action::DeleteAccount(_) = action {
+        let is_delete_account = matches!(action, Action::DeleteAccount(_));
+        // TODO(nonrefundable) Merge with the check above on stabilization.
+        #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+        let is_delete_account = is_delete_account || matches!(action, Action::DeleteAccountV2(_));
+        if is_delete_account {
             if iter.peek().is_some() {
                 return Err(ActionsValidationError::DeleteActionMustBeFinal);
             }
         } else {
             if let Action::Delegate(_) = action {
-                if !checked_feature!("stable", DelegateAction, current_protocol_version) {
-                    return Err(ActionsValidationError::UnsupportedProtocolFeature {
-                        protocol_feature: String::from("DelegateAction"),
-                        version: ProtocolFeature::DelegateAction.protocol_version(),
-                    });
-                }
+                ensure_protocol_feature_enabled(
+                    ProtocolFeature::DelegateAction,
+                    current_protocol_version,
+                )?;
                 if found_delegate_action {
                     return Err(ActionsValidationError::DelegateActionMustBeOnlyOne);
                 }
@@ -402,13 +412,27 @@ pub fn validate_action(
         Action::FunctionCall(a) => validate_function_call_action(limit_config, a),
         Action::Transfer(_) => Ok(()),
         #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
-        Action::NonrefundableStorageTransfer(_) => {
-            check_feature_enabled(ProtocolFeature::NonRefundableBalance, current_protocol_version)
+        Action::NonrefundableStorageTransfer(a) => {
+            ensure_protocol_feature_enabled(
+                ProtocolFeature::NonRefundableBalance,
+                current_protocol_version,
+            )?;
+            validate_nonrefundable_storage_transfer_action(a)
         }
         Action::Stake(a) => validate_stake_action(a),
         Action::AddKey(a) => validate_add_key_action(limit_config, a),
         Action::DeleteKey(_) => Ok(()),
         Action::DeleteAccount(a) => validate_delete_action(a),
+        #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+        Action::DeleteAccountV2(a) => {
+            ensure_protocol_feature_enabled(
+                ProtocolFeature::NonRefundableBalance,
+                current_protocol_version,
+            )?;
+            validate_delete_action(&DeleteAccountAction {
+                beneficiary_id: a.beneficiary_id.clone(),
+            })
+        }
         Action::Delegate(a) => validate_delegate_action(limit_config, a, current_protocol_version),
     }
 }
@@ -420,6 +444,11 @@ fn validate_delegate_action(
 ) -> Result<(), ActionsValidationError> {
     let actions = signed_delegate_action.delegate_action.get_actions();
     validate_actions(limit_config, &actions, current_protocol_version)?;
+    #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+    validate_nonrefundable_transfer_receiver(
+        &signed_delegate_action.delegate_action.receiver_id,
+        &actions,
+    )?;
     Ok(())
 }
 
@@ -534,8 +563,46 @@ fn validate_delete_action(action: &DeleteAccountAction) -> Result<(), ActionsVal
     Ok(())
 }
 
+/// Validates `NonrefundableStorageTransferAction`.
+///
+/// Checks that the deposit is positive, a zero deposit would only burn gas.
+#[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+fn validate_nonrefundable_storage_transfer_action(
+    action: &NonrefundableStorageTransferAction,
+) -> Result<(), ActionsValidationError> {
+    if action.deposit == 0 {
+        return Err(ActionsValidationError::NonrefundableTransferAmountZero);
+    }
+
+    Ok(())
+}
+
+/// Implicit accounts can only be created by a transfer, hence a non-refundable
+/// transfer to an implicit account must be the only action of the transaction.
 #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
-fn check_feature_enabled(
+fn validate_nonrefundable_transfer_receiver(
+    receiver_id: &AccountId,
+    actions: &[Action],
+) -> Result<(), ActionsValidationError> {
+    if receiver_id.get_account_type().is_implicit()
+        && actions.len() > 1
+        && actions.iter().any(|action| matches!(action, Action::NonrefundableStorageTransfer(_)))
+    {
+        return Err(
+            ActionsValidationError::NonrefundableTransferToImplicitAccountMustBeOnlyAction {
+                receiver_id: receiver_id.clone(),
+            },
+        );
+    }
+    Ok(())
+}
+
+/// Rejects actions gated by `feature` before the protocol version the feature
+/// is enabled in.
+///
+/// New actions gated by a protocol feature should be validated with it, so
+/// that all of them are rejected with the same error.
+pub fn ensure_protocol_feature_enabled(
     feature: ProtocolFeature,
     current_protocol_version: ProtocolVersion,
 ) -> Result<(), ActionsValidationError> {
@@ -819,6 +886,40 @@ mod tests {
         }
     }
 
+    /// Refundable and non-refundable balance together pay for the storage
+    /// exceeding the zero balance account limit.
+    #[test]
+    #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+    fn test_storage_stake_zero_balance_and_nonrefundable() {
+        let config = RuntimeConfig::test();
+        let storage_usage = ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT + 1;
+        let required_amount = Balance::from(storage_usage) * config.storage_amount_per_byte();
+        let account = |amount, nonrefundable, storage_usage| {
+            Account::new(
+                amount,
+                0,
+                nonrefundable,
+                CryptoHash::default(),
+                storage_usage,
+                PROTOCOL_VERSION,
+            )
+        };
+
+        let unfunded_zero_balance_account = account(0, 0, ZERO_BALANCE_ACCOUNT_STORAGE_LIMIT);
+        assert!(
+            check_storage_stake(&unfunded_zero_balance_account, &config, PROTOCOL_VERSION).is_ok()
+        );
+
+        let sponsored_account = account(0, required_amount, storage_usage);
+        assert!(check_storage_stake(&sponsored_account, &config, PROTOCOL_VERSION).is_ok());
+
+        let underfunded_account = account(1, required_amount - 2, storage_usage);
+        assert!(matches!(
+            check_storage_stake(&underfunded_account, &config, PROTOCOL_VERSION),
+            Err(StorageStakingError::LackBalanceForStorageStaking(1))
+        ));
+    }
+
     // Transactions
 
     #[test]
@@ -1073,6 +1174,7 @@ mod tests {
             signer_id,
             balance,
             cost,
+            ..
         }) = err
         {
             assert_eq!(signer_id, alice_account());
@@ -1764,6 +1866,30 @@ mod tests {
         .expect("valid action");
     }
 
+    #[test]
+    #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+    fn test_validate_action_invalid_nonrefundable_transfer_zero_deposit() {
+        validate_action(
+            &test_limit_config(),
+            &Action::NonrefundableStorageTransfer(NonrefundableStorageTransferAction {
+                deposit: 1,
+            }),
+            PROTOCOL_VERSION,
+        )
+        .expect("valid action");
+        assert_eq!(
+            validate_action(
+                &test_limit_config(),
+                &Action::NonrefundableStorageTransfer(NonrefundableStorageTransferAction {
+                    deposit: 0
+                }),
+                PROTOCOL_VERSION,
+            )
+            .expect_err("expected an error"),
+            ActionsValidationError::NonrefundableTransferAmountZero,
+        );
+    }
+
     #[test]
     fn test_validate_action_valid_stake() {
         validate_action(
@@ -1898,6 +2024,69 @@ mod tests {
         );
     }
 
+    /// The receiver of the inner actions of a delegate action is checked like
+    /// the receiver of a transaction.
+    #[test]
+    #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+    fn test_delegate_action_nonrefundable_transfer_to_implicit_account() {
+        let receiver_id: AccountId = "a".repeat(64).parse().unwrap();
+        let delegate_action = |actions: Vec<Action>| {
+            Action::Delegate(Box::new(SignedDelegateAction {
+                delegate_action: DelegateAction {
+                    sender_id: "bob.test.near".parse().unwrap(),
+                    receiver_id: receiver_id.clone(),
+                    actions: actions
+                        .into_iter()
+                        .map(|action| NonDelegateAction::try_from(action).unwrap())
+                        .collect(),
+                    nonce: 19000001,
+                    max_block_height: 57,
+                    public_key: PublicKey::empty(KeyType::ED25519),
+                },
+                signature: Signature::default(),
+            }))
+        };
+        let nonrefundable_transfer =
+            Action::NonrefundableStorageTransfer(NonrefundableStorageTransferAction { deposit: 1 });
+
+        assert_eq!(
+            validate_actions(
+                &test_limit_config(),
+                &[delegate_action(vec![nonrefundable_transfer.clone()])],
+                PROTOCOL_VERSION,
+            ),
+            Ok(()),
+        );
+        assert_eq!(
+            validate_actions(
+                &test_limit_config(),
+                &[delegate_action(vec![
+                    nonrefundable_transfer,
+                    Action::Transfer(TransferAction { deposit: 1 }),
+                ])],
+                PROTOCOL_VERSION,
+            ),
+            Err(ActionsValidationError::NonrefundableTransferToImplicitAccountMustBeOnlyAction {
+                receiver_id,
+            }),
+        );
+    }
+
+    #[test]
+    fn test_ensure_protocol_feature_enabled() {
+        let feature = ProtocolFeature::NonRefundableBalance;
+        let version = feature.protocol_version();
+        assert_eq!(
+            ensure_protocol_feature_enabled(feature, version - 1),
+            Err(ActionsValidationError::UnsupportedProtocolFeature {
+                protocol_feature: "NonRefundableBalance".to_string(),
+                version,
+            })
+        );
+        assert_eq!(ensure_protocol_feature_enabled(feature, version), Ok(()));
+        assert_eq!(ensure_protocol_feature_enabled(feature, version + 1), Ok(()));
+    }
+
     #[test]
     fn test_truncate_string() {
         fn check(input: &str, limit: usize, want: &str) {
@@ -1,8 +1,54 @@
 //! Helper functions to compute the costs of certain actions assuming they succeed and the only
 //! actions in the transaction batch.
+use near_crypto::{KeyType, PublicKey};
 use near_parameters::{ActionCosts, RuntimeConfig, RuntimeFeesConfig};
+use near_primitives::account::AccessKey;
+use near_primitives::borsh;
 use near_primitives::transaction::Action;
-use near_primitives::types::{AccountId, Balance, Gas};
+use near_primitives::types::{AccountId, Balance, Gas, StorageUsage};
+use near_primitives::views::AccountView;
+use std::collections::BTreeMap;
+
+/// Components of the cost of a transaction with a single `DeleteAccount` action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeleteCostBreakdown {
+    /// Send and exec cost of the action receipt.
+    pub receipt_creation: Balance,
+    /// Send and exec cost of the `DeleteAccount` action.
+    pub action_base: Balance,
+}
+
+impl DeleteCostBreakdown {
+    pub fn total(&self) -> Balance {
+        self.receipt_creation + self.action_base
+    }
+}
+
+/// How much of the storage used by an account is paid for by its balance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageCoverage {
+    /// Number of bytes the account balance pays storage staking for.
+    pub covered_bytes: u128,
+    pub used_bytes: StorageUsage,
+}
+
+impl StorageCoverage {
+    pub fn ratio(&self) -> f64 {
+        self.covered_bytes as f64 / self.used_bytes as f64
+    }
+
+    /// Whether the balance covers all used storage. Note that zero balance
+    /// accounts are valid without that.
+    pub fn is_funded(&self) -> bool {
+        self.covered_bytes >= self.used_bytes as u128
+    }
+
+    /// Number of bytes the account could still add to its state with its
+    /// current balance, negative if it uses more than its balance covers.
+    pub fn headroom(&self) -> i128 {
+        self.covered_bytes as i128 - self.used_bytes as i128
+    }
+}
 
 pub struct FeeHelper {
     pub rt_cfg: RuntimeConfig,
@@ -94,6 +140,13 @@ impl FeeHelper {
         self.gas_to_balance(exec_gas + send_gas)
     }
 
+    /// Gas burnt for executing a `DeployContract` action with code of the
+    /// given size, excluding the cost of the action receipt itself.
+    pub fn deploy_contract_gas(&self, code_size: u64) -> Gas {
+        self.cfg().fee(ActionCosts::deploy_contract_base).exec_fee()
+            + code_size * self.cfg().fee(ActionCosts::deploy_contract_byte).exec_fee()
+    }
+
     pub fn deploy_contract_cost(&self, num_bytes: u64) -> Balance {
         let exec_gas = self.cfg().fee(ActionCosts::new_action_receipt).exec_fee()
             + self.cfg().fee(ActionCosts::deploy_contract_base).exec_fee()
@@ -130,6 +183,34 @@ impl FeeHelper {
         self.gas_to_balance(self.transfer_fee())
     }
 
+    /// Non-refundable transfers are currently charged with the regular transfer
+    /// fees, see `runtime::config::total_send_fees`.
+    pub fn nonrefundable_transfer_fee(&self) -> Gas {
+        let exec_gas = self.cfg().fee(ActionCosts::new_action_receipt).exec_fee()
+            + self.cfg().fee(ActionCosts::transfer).exec_fee();
+        let send_gas = self.cfg().fee(ActionCosts::new_action_receipt).send_fee(false)
+            + self.cfg().fee(ActionCosts::transfer).send_fee(false);
+        exec_gas + send_gas
+    }
+
+    pub fn nonrefundable_transfer_cost(&self) -> Balance {
+        self.gas_to_balance(self.nonrefundable_transfer_fee())
+    }
+
+    /// Includes the surcharge for creating a NEAR-implicit account, which also
+    /// adds its full access key.
+    pub fn nonrefundable_transfer_to_implicit_account_fee(&self) -> Gas {
+        let exec_gas = self.cfg().fee(ActionCosts::create_account).exec_fee()
+            + self.cfg().fee(ActionCosts::add_full_access_key).exec_fee();
+        let send_gas = self.cfg().fee(ActionCosts::create_account).send_fee(false)
+            + self.cfg().fee(ActionCosts::add_full_access_key).send_fee(false);
+        self.nonrefundable_transfer_fee() + exec_gas + send_gas
+    }
+
+    pub fn nonrefundable_transfer_cost_to_implicit_account(&self) -> Balance {
+        self.gas_to_balance(self.nonrefundable_transfer_to_implicit_account_fee())
+    }
+
     pub fn stake_cost(&self) -> Balance {
         let exec_gas = self.cfg().fee(ActionCosts::new_action_receipt).exec_fee()
             + self.cfg().fee(ActionCosts::stake).exec_fee();
@@ -164,6 +245,28 @@ impl FeeHelper {
         self.gas_to_balance(exec_gas + send_gas)
     }
 
+    /// Costs of the common actions, keyed by stable names, so that tests can
+    /// compare them against golden values and catch unintended fee changes.
+    pub fn snapshot(&self) -> BTreeMap<String, Balance> {
+        let deploy_contract_per_byte = self.deploy_contract_cost(1) - self.deploy_contract_cost(0);
+        [
+            ("add_key_full", self.add_key_full_cost()),
+            ("create_account", self.create_account_cost()),
+            ("create_account_transfer_full_key", self.create_account_transfer_full_key_cost()),
+            ("delete_account", self.prepaid_delete_account_cost()),
+            ("deploy_contract_per_byte", deploy_contract_per_byte),
+            ("nonrefundable_transfer", self.nonrefundable_transfer_cost()),
+            (
+                "nonrefundable_transfer_to_implicit_account",
+                self.nonrefundable_transfer_cost_to_implicit_account(),
+            ),
+            ("transfer", self.transfer_cost()),
+        ]
+        .into_iter()
+        .map(|(name, cost)| (name.to_string(), cost))
+        .collect()
+    }
+
     pub fn prepaid_delete_account_cost(&self) -> Balance {
         let exec_gas = self.cfg().fee(ActionCosts::new_action_receipt).exec_fee()
             + self.cfg().fee(ActionCosts::delete_account).exec_fee();
@@ -175,6 +278,118 @@ impl FeeHelper {
         self.gas_to_balance(total_fee)
     }
 
+    /// Balance of the beneficiary after an account with `refunded_amount`
+    /// refundable balance deleted itself in favor of it.
+    ///
+    /// The deleted account pays for its own deletion, so the delete cost is
+    /// taken from the amount the beneficiary receives. Non-refundable balance
+    /// is burnt and never reaches the beneficiary.
+    pub fn expected_beneficiary_balance(
+        &self,
+        before: Balance,
+        refunded_amount: Balance,
+    ) -> Balance {
+        before + refunded_amount - self.prepaid_delete_account_cost()
+    }
+
+    pub fn delete_account_cost_breakdown(&self) -> DeleteCostBreakdown {
+        let receipt = self.cfg().fee(ActionCosts::new_action_receipt);
+        let delete_account = self.cfg().fee(ActionCosts::delete_account);
+        DeleteCostBreakdown {
+            receipt_creation: self.gas_to_balance(receipt.exec_fee() + receipt.send_fee(false)),
+            action_base: self
+                .gas_to_balance(delete_account.exec_fee() + delete_account.send_fee(false)),
+        }
+    }
+
+    /// Storage coverage of the account, counting the same balances as the
+    /// storage staking check of the runtime does.
+    pub fn storage_coverage(&self, account: &AccountView) -> StorageCoverage {
+        let balance = account.amount + account.locked;
+        #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+        let balance = balance + account.nonrefundable;
+        StorageCoverage {
+            covered_bytes: balance / self.rt_cfg.storage_amount_per_byte(),
+            used_bytes: account.storage_usage,
+        }
+    }
+
+    /// The part of the refundable balance of the account that cannot be
+    /// withdrawn because it pays for storage not covered by the locked and the
+    /// non-refundable balance. Ignores that zero balance accounts need no
+    /// balance for their storage at all.
+    pub fn refundable_storage_floor(&self, account: &AccountView) -> Balance {
+        let required = account.storage_usage as Balance * self.rt_cfg.storage_amount_per_byte();
+        let covered = account.locked;
+        #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+        let covered = covered + account.nonrefundable;
+        required.saturating_sub(covered)
+    }
+
+    /// Storage usage of a new account with a single full access ED25519 key and
+    /// no contract.
+    pub fn account_with_full_access_key_storage_usage(&self) -> StorageUsage {
+        let storage_config = &self.rt_cfg.fees.storage_usage_config;
+        let key_bytes = borsh::object_length(&PublicKey::empty(KeyType::ED25519)).unwrap()
+            + borsh::object_length(&AccessKey::full_access()).unwrap();
+        storage_config.num_bytes_account + key_bytes as u64 + storage_config.num_extra_bytes_record
+    }
+
+    /// Contract which, deployed on a new account with a single full access
+    /// ED25519 key, makes the `storage_usage` of the account exactly
+    /// `target_storage_usage`.
+    ///
+    /// The code itself is stored without additional overhead, so only the account
+    /// and the access key records have to be subtracted from the target.
+    pub fn contract_for_storage_bytes(&self, target_storage_usage: StorageUsage) -> Vec<u8> {
+        let empty_account_storage_usage = self.account_with_full_access_key_storage_usage();
+        let code_size =
+            target_storage_usage.checked_sub(empty_account_storage_usage).unwrap_or_else(|| {
+                panic!(
+                    "target storage usage {target_storage_usage} is below the storage usage \
+                     {empty_account_storage_usage} of the account without contract"
+                )
+            });
+        near_test_contracts::sized_contract(code_size as usize)
+    }
+
+    /// Contract which, deployed on a new account with a single full access
+    /// ED25519 key, uses exactly the storage paid for by `nonrefundable`.
+    pub fn contract_for_nonrefundable(&self, nonrefundable: Balance) -> Vec<u8> {
+        let covered_bytes = nonrefundable / self.rt_cfg.storage_amount_per_byte();
+        self.contract_for_storage_bytes(covered_bytes.try_into().unwrap())
+    }
+
+    /// The minimum gas a transaction with the given actions has to pay for: the
+    /// send and exec fees of the action receipt and of all actions, plus the gas
+    /// attached to function calls.
+    pub fn min_attached_gas(&self, actions: &[Action], receiver: &AccountId) -> Gas {
+        self.min_send_gas(actions, receiver) + self.min_exec_gas(actions, receiver)
+    }
+
+    /// The balance the signer needs to pay for `min_attached_gas`.
+    ///
+    /// Exec gas is prepaid at the gas price inflated for a single receipt hop,
+    /// hence this is only exact for transactions without function calls.
+    pub fn min_attached_gas_cost(&self, actions: &[Action], receiver: &AccountId) -> Balance {
+        self.gas_to_balance(self.min_send_gas(actions, receiver))
+            + self.gas_to_balance_inflated(self.min_exec_gas(actions, receiver))
+    }
+
+    fn min_send_gas(&self, actions: &[Action], receiver: &AccountId) -> Gas {
+        // for tests, we assume sender != receiver
+        let sir = false;
+        self.cfg().fee(ActionCosts::new_action_receipt).send_fee(sir)
+            + node_runtime::config::total_send_fees(&self.rt_cfg, sir, actions, receiver).unwrap()
+    }
+
+    fn min_exec_gas(&self, actions: &[Action], receiver: &AccountId) -> Gas {
+        self.cfg().fee(ActionCosts::new_action_receipt).exec_fee()
+            + node_runtime::config::total_prepaid_exec_fees(&self.rt_cfg, actions, receiver)
+                .unwrap()
+            + node_runtime::config::total_prepaid_gas(actions).unwrap()
+    }
+
     /// The additional cost to execute a list of actions in a meta transaction,
     /// compared to executing them directly.
     ///
@@ -0,0 +1,125 @@
+//! Builder to compose signed transactions out of several actions in tests.
+use near_crypto::{InMemorySigner, PublicKey};
+use near_primitives::account::{AccessKey, AccessKeyPermission};
+use near_primitives::hash::CryptoHash;
+#[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+use near_primitives::transaction::NonrefundableStorageTransferAction;
+use near_primitives::transaction::{
+    Action, AddKeyAction, CreateAccountAction, DeployContractAction, SignedTransaction,
+    TransferAction,
+};
+use near_primitives::types::{AccountId, Balance, Nonce};
+
+/// Reasons for [`TransactionBuilder::build`] to refuse building a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransactionBuilderError {
+    /// Implicit accounts can only be created by a transfer, never by `CreateAccount`.
+    CreateImplicitAccount { account_id: AccountId },
+    /// Non-refundable transfer to an implicit account must be the only action,
+    /// as it is only allowed when it creates the account.
+    NonrefundableTransferToImplicitAccountWithOtherActions { account_id: AccountId },
+}
+
+/// Collects actions in the order the builder methods are called.
+#[derive(Default, Clone)]
+pub struct TransactionBuilder {
+    actions: Vec<Action>,
+}
+
+impl TransactionBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Panics if other actions were added before, as they would be applied to
+    /// an account that does not exist yet.
+    pub fn create_account(mut self) -> Self {
+        assert!(
+            self.actions.is_empty(),
+            "create_account must be the first action, but it follows {:?}",
+            self.actions
+        );
+        self.actions.push(Action::CreateAccount(CreateAccountAction {}));
+        self
+    }
+
+    /// Adds a full access key.
+    pub fn add_key(mut self, public_key: PublicKey) -> Self {
+        self.actions.push(Action::AddKey(Box::new(AddKeyAction {
+            public_key,
+            access_key: AccessKey { nonce: 0, permission: AccessKeyPermission::FullAccess },
+        })));
+        self
+    }
+
+    #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+    pub fn nonrefundable_transfer(mut self, deposit: Balance) -> Self {
+        self.actions.push(Action::NonrefundableStorageTransfer(
+            NonrefundableStorageTransferAction { deposit },
+        ));
+        self
+    }
+
+    pub fn transfer(mut self, deposit: Balance) -> Self {
+        self.actions.push(Action::Transfer(TransferAction { deposit }));
+        self
+    }
+
+    pub fn deploy(mut self, code: Vec<u8>) -> Self {
+        self.actions.push(Action::DeployContract(DeployContractAction { code }));
+        self
+    }
+
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+
+    /// Signs the collected actions into a transaction.
+    ///
+    /// Fails if the actions can never succeed on an implicit `receiver`,
+    /// without checking whether the receiver exists.
+    pub fn build(
+        self,
+        signer: &InMemorySigner,
+        receiver: AccountId,
+        nonce: Nonce,
+        block_hash: CryptoHash,
+    ) -> Result<SignedTransaction, TransactionBuilderError> {
+        if receiver.get_account_type().is_implicit() {
+            self.check_implicit_account_actions(&receiver)?;
+        }
+        Ok(SignedTransaction::from_actions(
+            nonce,
+            signer.account_id.clone(),
+            receiver,
+            signer,
+            self.actions,
+            block_hash,
+        ))
+    }
+
+    fn check_implicit_account_actions(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<(), TransactionBuilderError> {
+        if self.actions.iter().any(|action| matches!(action, Action::CreateAccount(_))) {
+            return Err(TransactionBuilderError::CreateImplicitAccount {
+                account_id: account_id.clone(),
+            });
+        }
+        #[cfg(feature = "protocol_feature_nonrefundable_transfer_nep491")]
+        if self.actions.len() > 1
+            && self
+                .actions
+                .iter()
+                .any(|action| matches!(action, Action::NonrefundableStorageTransfer(_)))
+        {
+            return Err(
+                TransactionBuilderError::NonrefundableTransferToImplicitAccountWithOtherActions {
+                    account_id: account_id.clone(),
+                },
+            );
+        }
+        Ok(())
+    }
+}
@@ -346,6 +346,10 @@ fn try_find_actions_spawned_by_receipt(
                                     Action::AddKey(_) => ActionType::AddKey,
                                     Action::DeleteKey(_) => ActionType::DeleteKey,
                                     Action::DeleteAccount(_) => ActionType::DeleteAccount,
+                                    #[cfg(
+                                        feature = "protocol_feature_nonrefundable_transfer_nep491"
+                                    )]
+                                    Action::DeleteAccountV2(_) => ActionType::DeleteAccount,
                                     Action::Delegate(_) => ActionType::Delegate,
                                 };
                                 entry